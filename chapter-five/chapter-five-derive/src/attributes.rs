@@ -0,0 +1,70 @@
+// Helpers for reading the attributes of our derives.
+// Every mistake is returned as a syn::Error spanning the offending tokens,
+// which the derive then turns into a compile_error! at that exact spot
+
+// Finds #[name = "value"], which may appear at most once
+pub fn name_value(attrs: &[syn::Attribute], name: &str) -> syn::Result<Option<syn::LitStr>> {
+    let mut found = None;
+    for attr in attrs.iter().filter(|a| a.path.is_ident(name)) {
+        if found.is_some() {
+            return Err(syn::Error::new_spanned(
+                attr,
+                format!("#[{}] can only be used once here", name),
+            ));
+        }
+        match attr.parse_meta()? {
+            syn::Meta::NameValue(pair) => found = Some(string_literal(&pair)?),
+            other => {
+                return Err(syn::Error::new_spanned(
+                    other,
+                    format!("Expected an attribute in the form #[{} = \"Some value\"]", name),
+                ))
+            }
+        }
+    }
+    Ok(found)
+}
+
+// Collects everything inside of all #[name(...)] attributes
+pub fn list(attrs: &[syn::Attribute], name: &str) -> syn::Result<Vec<syn::Meta>> {
+    let mut metas = Vec::new();
+    for attr in attrs.iter().filter(|a| a.path.is_ident(name)) {
+        match attr.parse_meta()? {
+            syn::Meta::List(list) => {
+                for nested in list.nested {
+                    match nested {
+                        syn::NestedMeta::Meta(meta) => metas.push(meta),
+                        syn::NestedMeta::Lit(lit) => {
+                            return Err(syn::Error::new_spanned(lit, "Expected a name, not a literal"))
+                        }
+                    }
+                }
+            }
+            other => {
+                return Err(syn::Error::new_spanned(
+                    other,
+                    format!("Expected an attribute in the form #[{}(...)]", name),
+                ))
+            }
+        }
+    }
+    Ok(metas)
+}
+
+// Fails on the first #[name] found, for places where it has no meaning
+pub fn forbid(attrs: &[syn::Attribute], name: &str, place: &str) -> syn::Result<()> {
+    match attrs.iter().find(|a| a.path.is_ident(name)) {
+        Some(attr) => Err(syn::Error::new_spanned(
+            attr,
+            format!("#[{}] can't be used on {}", name, place),
+        )),
+        None => Ok(()),
+    }
+}
+
+pub fn string_literal(pair: &syn::MetaNameValue) -> syn::Result<syn::LitStr> {
+    match pair.lit {
+        syn::Lit::Str(ref value) => Ok(value.clone()),
+        ref other => Err(syn::Error::new_spanned(other, "Expected a string")),
+    }
+}
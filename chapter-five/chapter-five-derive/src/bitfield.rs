@@ -0,0 +1,306 @@
+use quote::{format_ident, quote};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+
+// bitfield! {
+//     #[derive(Clone, Copy)]
+//     pub struct Header: u16 {
+//         #[bits(4)]
+//         version: u8,
+//         ...
+//     }
+// }
+pub struct BitfieldInput {
+    attrs: Vec<syn::Attribute>,
+    vis: syn::Visibility,
+    ident: syn::Ident,
+    backing: syn::Ident,
+    fields: Punctuated<syn::Field, syn::Token![,]>,
+}
+
+impl Parse for BitfieldInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let attrs = input.call(syn::Attribute::parse_outer)?;
+        let vis = input.parse()?;
+        input.parse::<syn::Token![struct]>()?;
+        let ident = input.parse()?;
+        input.parse::<syn::Token![:]>()?;
+        let backing = input.parse()?;
+        let content;
+        syn::braced!(content in input);
+        let fields = content.parse_terminated(syn::Field::parse_named)?;
+        Ok(BitfieldInput {
+            attrs,
+            vis,
+            ident,
+            backing,
+            fields,
+        })
+    }
+}
+
+// How a field is turned into bits and back
+enum Kind {
+    Bool,
+    Unsigned(u32),
+    // Anything else, like an enum, is converted with
+    // From<T> for u64 and TryFrom<u64> for T
+    Other,
+}
+
+fn integer_bits(ty: &syn::Type) -> Option<u32> {
+    let ident = match *ty {
+        syn::Type::Path(ref path) => path.path.get_ident()?,
+        _ => return None,
+    };
+    match ident.to_string().as_str() {
+        "u8" => Some(8),
+        "u16" => Some(16),
+        "u32" => Some(32),
+        "u64" => Some(64),
+        _ => None,
+    }
+}
+
+fn kind(ty: &syn::Type) -> syn::Result<Kind> {
+    if let syn::Type::Path(ref path) = *ty {
+        if let Some(ident) = path.path.get_ident() {
+            match ident.to_string().as_str() {
+                "bool" => return Ok(Kind::Bool),
+                "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u128" | "usize" => {
+                    return Err(syn::Error::new_spanned(
+                        ty,
+                        "Only u8, u16, u32, u64, bool and enums can be stored in a bitfield",
+                    ))
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(integer_bits(ty).map_or(Kind::Other, Kind::Unsigned))
+}
+
+// Reads #[bits(n)], which can be left out for bools and integers using all their bits
+fn width(field: &syn::Field, kind: &Kind) -> syn::Result<u32> {
+    let attr = match field.attrs.iter().find(|a| a.path.is_ident("bits")) {
+        Some(attr) => attr,
+        None => {
+            return match *kind {
+                Kind::Bool => Ok(1),
+                Kind::Unsigned(bits) => Ok(bits),
+                Kind::Other => Err(syn::Error::new_spanned(
+                    field,
+                    "Fields that are not bools or integers need a #[bits(n)]",
+                )),
+            }
+        }
+    };
+    let literal: syn::LitInt = attr.parse_args()?;
+    let width: u32 = literal.base10_parse()?;
+    let maximum = match *kind {
+        Kind::Bool => 1,
+        Kind::Unsigned(bits) => bits,
+        Kind::Other => 64,
+    };
+    if width == 0 || width > maximum {
+        let ty = &field.ty;
+        return Err(syn::Error::new_spanned(
+            literal,
+            format!("A {} needs between 1 and {} bits", quote!(#ty), maximum),
+        ));
+    }
+    Ok(width)
+}
+
+pub fn impl_bitfield(input: &BitfieldInput) -> syn::Result<proc_macro2::TokenStream> {
+    let BitfieldInput {
+        ref attrs,
+        ref vis,
+        ref ident,
+        ref backing,
+        ref fields,
+    } = *input;
+    let backing_type: syn::Type = syn::parse_quote!(#backing);
+    let total_bits = integer_bits(&backing_type).ok_or_else(|| {
+        syn::Error::new_spanned(backing, "A bitfield has to be backed by u8, u16, u32 or u64")
+    })?;
+    let bytes = (total_bits / 8) as usize;
+
+    // Like in the diagrams of network protocols, the first field
+    // takes the most significant bits and the fields follow in order
+    let mut used_bits = 0;
+    let mut reserved_mask = 0u64;
+    let mut accessors = Vec::new();
+    let mut debug_fields = Vec::new();
+    for field in fields {
+        let kind = kind(&field.ty)?;
+        let width = width(field, &kind)?;
+        used_bits += width;
+        if used_bits > total_bits {
+            return Err(syn::Error::new_spanned(
+                field,
+                format!("The fields need more than the {} bits of {}", total_bits, backing),
+            ));
+        }
+        let offset = total_bits - used_bits;
+
+        let name = field.ident.as_ref().expect("Fields are parsed as named fields");
+        // Fields starting with an underscore are reserved bits, which stay zero
+        if name.to_string().starts_with('_') {
+            reserved_mask |= (u64::MAX >> (64 - width)) << offset;
+            continue;
+        }
+        let field_type = &field.ty;
+        let setter = format_ident!("set_{}", name);
+        let docs = field.attrs.iter().filter(|a| a.path.is_ident("doc"));
+        let mask = proc_macro2::Literal::u64_unsuffixed(u64::MAX >> (64 - width));
+        let offset = proc_macro2::Literal::u32_unsuffixed(offset);
+        let raw = quote! { ((self.0 >> #offset) & #mask) };
+        let store = quote! {
+            self.0 = (self.0 & !(#mask << #offset)) | ((raw as #backing) << #offset);
+        };
+        // No need to check for overflows when every value of the type fits
+        let overflow_check = quote! {
+            if raw > #mask {
+                return ::std::result::Result::Err(format!(
+                    "{} doesn't fit into the {} bits of {}",
+                    raw,
+                    #width,
+                    stringify!(#name)
+                ));
+            }
+        };
+        let overflow_check = match kind {
+            Kind::Unsigned(bits) if bits == width => quote! {},
+            _ => overflow_check,
+        };
+
+        accessors.push(match kind {
+            Kind::Bool => quote! {
+                #(#docs)*
+                #vis fn #name(&self) -> bool {
+                    #raw != 0
+                }
+
+                #vis fn #setter(&mut self, value: bool) {
+                    let raw = u64::from(value);
+                    #store
+                }
+            },
+            Kind::Unsigned(_) => quote! {
+                #(#docs)*
+                #vis fn #name(&self) -> #field_type {
+                    #raw as #field_type
+                }
+
+                #vis fn #setter(&mut self, value: #field_type) -> ::std::result::Result<(), String> {
+                    let raw = u64::from(value);
+                    #overflow_check
+                    #store
+                    ::std::result::Result::Ok(())
+                }
+            },
+            // The bits could contain a value that doesn't belong to the enum
+            Kind::Other => quote! {
+                #(#docs)*
+                #vis fn #name(&self) -> ::std::result::Result<#field_type, <#field_type as ::std::convert::TryFrom<u64>>::Error> {
+                    <#field_type as ::std::convert::TryFrom<u64>>::try_from(#raw as u64)
+                }
+
+                #vis fn #setter(&mut self, value: #field_type) -> ::std::result::Result<(), String> {
+                    let raw = u64::from(value);
+                    #overflow_check
+                    #store
+                    ::std::result::Result::Ok(())
+                }
+            },
+        });
+        debug_fields.push(match kind {
+            Kind::Other => quote! {
+                match self.#name() {
+                    ::std::result::Result::Ok(value) => debug.field(stringify!(#name), &value),
+                    ::std::result::Result::Err(_) => debug.field(
+                        stringify!(#name),
+                        &format_args!("invalid ({})", #raw),
+                    ),
+                };
+            },
+            _ => quote! {
+                debug.field(stringify!(#name), &self.#name());
+            },
+        });
+    }
+    // Unused bits have to be declared as reserved fields,
+    // so the layout is always spelled out completely
+    if used_bits != total_bits {
+        let unused = total_bits - used_bits;
+        let reserved_type = match unused {
+            1..=8 => "u8",
+            9..=16 => "u16",
+            17..=32 => "u32",
+            _ => "u64",
+        };
+        return Err(syn::Error::new_spanned(
+            backing,
+            format!(
+                "The fields use {} of the {} bits of {}, add a reserved field like #[bits({})] _reserved: {}",
+                used_bits, total_bits, backing, unused, reserved_type
+            ),
+        ));
+    }
+
+    let from_bits = if reserved_mask == 0 {
+        quote! { #ident(bits) }
+    } else {
+        let reserved_mask = proc_macro2::Literal::u64_unsuffixed(reserved_mask);
+        quote! { #ident(bits & !#reserved_mask) }
+    };
+
+    Ok(quote! {
+        #(#attrs)*
+        #vis struct #ident(#backing);
+
+        impl #ident {
+            // All bits set to zero
+            #vis const fn new() -> Self {
+                #ident(0)
+            }
+
+            // Whatever was sent in the reserved bits is dropped
+            #vis const fn from_bits(bits: #backing) -> Self {
+                #from_bits
+            }
+
+            #vis const fn bits(&self) -> #backing {
+                self.0
+            }
+
+            // Network protocols send their headers in big endian
+            #vis fn from_be_bytes(bytes: [u8; #bytes]) -> Self {
+                #ident::from_bits(#backing::from_be_bytes(bytes))
+            }
+
+            #vis fn to_be_bytes(&self) -> [u8; #bytes] {
+                self.0.to_be_bytes()
+            }
+
+            #vis fn from_le_bytes(bytes: [u8; #bytes]) -> Self {
+                #ident::from_bits(#backing::from_le_bytes(bytes))
+            }
+
+            #vis fn to_le_bytes(&self) -> [u8; #bytes] {
+                self.0.to_le_bytes()
+            }
+
+            #(#accessors)*
+        }
+
+        impl ::std::fmt::Debug for #ident {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                let mut debug = f.debug_struct(stringify!(#ident));
+                #(#debug_fields)*
+                debug.finish()
+            }
+        }
+    })
+}
@@ -1,27 +1,74 @@
 use quote::quote;
 use proc_macro::TokenStream;
 
+mod attributes;
+mod bitfield;
+mod validate;
+
 // HelloWorld is the name for the derive
-// hello_world_name is the name of our optional attribute
+// hello_world_name is the name of our optional attribute.
+// It can be put on the struct or enum itself, and on every variant of an enum
 #[proc_macro_derive(HelloWorld, attributes(hello_world_name))]
 pub fn hello_world(input: TokenStream) -> TokenStream {
     // Construct a representation of Rust code as a syntax tree
     // that we can manipulate
-    let ast = syn::parse(input).unwrap();
+    let ast = syn::parse_macro_input!(input as syn::DeriveInput);
 
-    // Build the implementation
+    // Build the implementation, or a compile_error! pointing
+    // at whatever is wrong with the input
     impl_hello_world(&ast)
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
 }
 
-fn impl_hello_world(ast: &syn::DeriveInput) -> TokenStream {
+const HELLO_WORLD_NAME: &str = "hello_world_name";
+
+fn impl_hello_world(ast: &syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
     let identifier = &ast.ident;
-    let identifier_str = identifier.to_string();
     // Use the name provided by the attribute
     // If there is no attribute, use the identifier
-    let hello_world_name = get_name_attribute(ast).unwrap_or_else(|| identifier_str);
-    let gen = quote! {
+    let hello_world_name = attributes::name_value(&ast.attrs, HELLO_WORLD_NAME)?
+        .map(|name| name.value())
+        .unwrap_or_else(|| identifier.to_string());
+
+    // Structs simply use their own name, while every variant of an enum
+    // can have a name of its own
+    let name_of_self = match ast.data {
+        syn::Data::Struct(ref data) => {
+            forbid_on_fields(&data.fields)?;
+            quote! { #hello_world_name }
+        }
+        syn::Data::Enum(ref data) => {
+            let mut arms = Vec::new();
+            for variant in &data.variants {
+                forbid_on_fields(&variant.fields)?;
+                let variant_identifier = &variant.ident;
+                let variant_name = attributes::name_value(&variant.attrs, HELLO_WORLD_NAME)?
+                    .map(|name| name.value())
+                    .unwrap_or_else(|| variant_identifier.to_string());
+                // { .. } matches unit and tuple variants as well
+                arms.push(quote! { #identifier::#variant_identifier { .. } => #variant_name });
+            }
+            quote! {
+                match *self {
+                    #(#arms,)*
+                }
+            }
+        }
+        syn::Data::Union(ref data) => {
+            return Err(syn::Error::new_spanned(
+                data.union_token,
+                "HelloWorld can only be derived for structs and enums",
+            ))
+        }
+    };
+
+    // Generic types get the same generics and bounds on the impl,
+    // so Wrapper<T> turns into impl<T> HelloWorld for Wrapper<T>
+    let (impl_generics, type_generics, where_clause) = ast.generics.split_for_impl();
+    Ok(quote! {
         // Insert an implementation for our trait
-        impl HelloWorld for #identifier {
+        impl #impl_generics HelloWorld for #identifier #type_generics #where_clause {
             fn hello_world() {
                 println!(
                     "The struct or enum {} says: \"Hello world from {}!\"",
@@ -29,34 +76,217 @@ fn impl_hello_world(ast: &syn::DeriveInput) -> TokenStream {
                     #hello_world_name
                 );
             }
+
+            fn hello_world_name(&self) -> &'static str {
+                #name_of_self
+            }
         }
-    };
-    gen.into()
+    })
 }
 
-fn get_name_attribute(ast: &syn::DeriveInput) -> Option<String> {
-    const ATTR_NAME: &str = "hello_world_name";
+fn forbid_on_fields(fields: &syn::Fields) -> syn::Result<()> {
+    for field in fields {
+        attributes::forbid(&field.attrs, HELLO_WORLD_NAME, "fields")?;
+    }
+    Ok(())
+}
+
+// EnumString is the name for the derive
+// enum_string is used both on the enum and on its variants:
+// #[enum_string(case_insensitive)] on the enum accepts "DOG" for Dog,
+// #[enum_string(rename = "dog", alias = "puppy")] on a variant
+// changes its name and adds more names it can be parsed from
+#[proc_macro_derive(EnumString, attributes(enum_string))]
+pub fn enum_string(input: TokenStream) -> TokenStream {
+    let ast = syn::parse_macro_input!(input as syn::DeriveInput);
+
+    // Mistakes are reported as compile errors pointing at the culprit,
+    // which is a lot more helpful than a panic inside the macro
+    impl_enum_string(&ast)
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
+}
+
+struct EnumStringVariant {
+    identifier: syn::Ident,
+    name: String,
+    aliases: Vec<String>,
+}
+
+fn impl_enum_string(ast: &syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let identifier = &ast.ident;
+    let data = match ast.data {
+        syn::Data::Enum(ref data) => data,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                identifier,
+                "EnumString can only be derived for enums",
+            ))
+        }
+    };
+
+    let mut case_insensitive = false;
+    for meta in attributes::list(&ast.attrs, "enum_string")? {
+        match meta {
+            syn::Meta::Path(ref path) if path.is_ident("case_insensitive") => {
+                case_insensitive = true
+            }
+            other => {
+                return Err(syn::Error::new_spanned(
+                    other,
+                    "Expected #[enum_string(case_insensitive)]",
+                ))
+            }
+        }
+    }
+
+    let mut variants = Vec::new();
+    for variant in &data.variants {
+        // There is no way to come up with the data from a string alone
+        if !matches!(variant.fields, syn::Fields::Unit) {
+            return Err(syn::Error::new_spanned(
+                &variant.fields,
+                "EnumString only supports variants without data",
+            ));
+        }
+        let mut name = variant.ident.to_string();
+        let mut aliases = Vec::new();
+        for meta in attributes::list(&variant.attrs, "enum_string")? {
+            match meta {
+                syn::Meta::NameValue(ref pair) if pair.path.is_ident("rename") => {
+                    name = attributes::string_literal(pair)?.value()
+                }
+                syn::Meta::NameValue(ref pair) if pair.path.is_ident("alias") => {
+                    aliases.push(attributes::string_literal(pair)?.value())
+                }
+                other => {
+                    return Err(syn::Error::new_spanned(
+                        other,
+                        "Expected #[enum_string(rename = \"...\")] or #[enum_string(alias = \"...\")]",
+                    ))
+                }
+            }
+        }
+        variants.push(EnumStringVariant {
+            identifier: variant.ident.clone(),
+            name,
+            aliases,
+        });
+    }
+    check_unique_names(&variants, case_insensitive, &ast.ident)?;
+
+    let variant_identifiers: Vec<_> = variants.iter().map(|v| &v.identifier).collect();
+    let names: Vec<_> = variants.iter().map(|v| &v.name).collect();
+    let variant_count = variants.len();
+    let expected = names
+        .iter()
+        .map(|name| name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let conditions = variants.iter().map(|variant| {
+        let comparisons = std::iter::once(&variant.name)
+            .chain(&variant.aliases)
+            .map(|name| {
+                if case_insensitive {
+                    quote! { s.eq_ignore_ascii_case(#name) }
+                } else {
+                    quote! { s == #name }
+                }
+            });
+        quote! { #(#comparisons)||* }
+    });
+    let (impl_generics, type_generics, where_clause) = ast.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::std::fmt::Display for #identifier #type_generics #where_clause {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                // pad instead of write_str, so {:<10} works as well
+                f.pad(match *self {
+                    #(#identifier::#variant_identifiers => #names,)*
+                })
+            }
+        }
 
-    // Go through all attributes and find one with our name
-    if let Some(attr) = ast.attrs.iter().find(|a| a.path.is_ident(ATTR_NAME)) {
-        // Check if it's in the form of a name-value pair
-        if let syn::Meta::NameValue(pair) = attr.parse_meta().ok()? {
-            // Check if the value is a string
-            if let syn::Lit::Str(name) = pair.lit {
-                Some(name.value())
+        impl #impl_generics ::std::str::FromStr for #identifier #type_generics #where_clause {
+            type Err = String;
+
+            fn from_str(s: &str) -> ::std::result::Result<Self, Self::Err> {
+                #(
+                    if #conditions {
+                        return ::std::result::Result::Ok(#identifier::#variant_identifiers);
+                    }
+                )*
+                ::std::result::Result::Err(format!(
+                    "'{}' is not a valid {}, expected one of: {}",
+                    s,
+                    stringify!(#identifier),
+                    #expected
+                ))
+            }
+        }
+
+        impl #impl_generics #identifier #type_generics #where_clause {
+            // All variants, in the order they were declared
+            pub fn iter() -> impl Iterator<Item = Self> {
+                [#(#identifier::#variant_identifiers),*].into_iter()
+            }
+
+            pub const fn variant_count() -> usize {
+                #variant_count
+            }
+        }
+    })
+}
+
+// Two variants with the same name could never both be parsed
+fn check_unique_names(
+    variants: &[EnumStringVariant],
+    case_insensitive: bool,
+    identifier: &syn::Ident,
+) -> syn::Result<()> {
+    let mut seen = std::collections::HashMap::new();
+    for variant in variants {
+        for name in std::iter::once(&variant.name).chain(&variant.aliases) {
+            let key = if case_insensitive {
+                name.to_ascii_lowercase()
             } else {
-                panic!(
-                    "Expected a string as the value of {}",
-                    ATTR_NAME
-                );
+                name.clone()
+            };
+            if let Some(other) = seen.insert(key, &variant.identifier) {
+                return Err(syn::Error::new_spanned(
+                    &variant.identifier,
+                    format!(
+                        "'{}' is used by both {}::{} and {}::{}",
+                        name, identifier, other, identifier, variant.identifier
+                    ),
+                ));
             }
-        } else {
-            panic!(
-                "Expected an attribute in the form #[{} = \"Some value\"]",
-                ATTR_NAME
-            );
         }
-    } else {
-        None
     }
+    Ok(())
+}
+
+// Validate is the name for the derive, validate the name of its attribute.
+// Like with HelloWorld, the consumer has to provide the types we use:
+// a Validate trait with fn validate_into(&self, errors: &mut ValidationErrors)
+// and a ValidationErrors with fn add(&mut self, message: String) and
+// fn field(&mut self, name: &str) -> &mut ValidationErrors.
+// regex and email checks also need lazy_static and regex as dependencies
+#[proc_macro_derive(Validate, attributes(validate))]
+pub fn validate(input: TokenStream) -> TokenStream {
+    let ast = syn::parse_macro_input!(input as syn::DeriveInput);
+    validate::impl_validate(&ast)
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
+}
+
+// Declares a struct over an integer, whose bits are split into fields:
+// bitfield! { pub struct Header: u8 { #[bits(4)] version: u8, ... } }
+// Every field gets a getter and a setter that checks for overflows
+#[proc_macro]
+pub fn bitfield(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as bitfield::BitfieldInput);
+    bitfield::impl_bitfield(&input)
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
 }
@@ -0,0 +1,279 @@
+use crate::attributes;
+use quote::quote;
+use syn::ext::IdentExt;
+
+// Everything a single #[validate(...)] on a field can ask for
+enum Check {
+    Range {
+        min: Option<syn::Lit>,
+        max: Option<syn::Lit>,
+    },
+    Length {
+        min: Option<syn::Lit>,
+        max: Option<syn::Lit>,
+    },
+    Regex(syn::LitStr),
+    Email,
+    Nested,
+    Custom(syn::Path),
+}
+
+// Good enough to catch typos, real verification means sending a mail
+const EMAIL_PATTERN: &str = r"^[^@\s]+@[^@\s]+\.[^@\s]+$";
+
+pub fn impl_validate(ast: &syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let identifier = &ast.ident;
+    let data = match ast.data {
+        syn::Data::Struct(ref data) => data,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                identifier,
+                "Validate can only be derived for structs",
+            ))
+        }
+    };
+
+    // Checks on the struct as a whole, usually comparing fields with each other
+    let mut struct_checks = Vec::new();
+    for meta in attributes::list(&ast.attrs, "validate")? {
+        match meta {
+            syn::Meta::NameValue(ref pair) if pair.path.is_ident("custom") => {
+                let function: syn::Path = attributes::string_literal(pair)?.parse()?;
+                struct_checks.push(quote! {
+                    if let ::std::result::Result::Err(message) = #function(self) {
+                        errors.add(message);
+                    }
+                });
+            }
+            other => {
+                return Err(syn::Error::new_spanned(
+                    other,
+                    "Expected #[validate(custom = \"function\")]",
+                ))
+            }
+        }
+    }
+
+    let mut field_checks = Vec::new();
+    for (index, field) in data.fields.iter().enumerate() {
+        // Tuple structs are validated by position, like my_tuple.0
+        let (member, name) = match field.ident {
+            Some(ref ident) => (quote! { #ident }, ident.unraw().to_string()),
+            None => {
+                let index = syn::Index::from(index);
+                (quote! { #index }, index.index.to_string())
+            }
+        };
+        let mut checks = Vec::new();
+        for meta in attributes::list(&field.attrs, "validate")? {
+            checks.push(parse_check(meta)?);
+        }
+        if checks.is_empty() {
+            continue;
+        }
+
+        // An Option is only validated when it contains something
+        let (inner_type, optional) = match generic_argument(&field.ty, "Option") {
+            Some(inner_type) => (inner_type, true),
+            None => (&field.ty, false),
+        };
+        let checks = checks
+            .iter()
+            .map(|check| check_tokens(check, inner_type, &name));
+        field_checks.push(if optional {
+            quote! {
+                if let ::std::option::Option::Some(value) = &self.#member {
+                    #(#checks)*
+                }
+            }
+        } else {
+            quote! {
+                {
+                    let value = &self.#member;
+                    #(#checks)*
+                }
+            }
+        });
+    }
+
+    let (impl_generics, type_generics, where_clause) = ast.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics Validate for #identifier #type_generics #where_clause {
+            fn validate_into(&self, errors: &mut ValidationErrors) {
+                #(#field_checks)*
+                #(#struct_checks)*
+            }
+        }
+    })
+}
+
+fn parse_check(meta: syn::Meta) -> syn::Result<Check> {
+    let check = match meta {
+        syn::Meta::Path(ref path) if path.is_ident("email") => Check::Email,
+        syn::Meta::Path(ref path) if path.is_ident("nested") => Check::Nested,
+        syn::Meta::NameValue(ref pair) if pair.path.is_ident("regex") => {
+            let pattern = attributes::string_literal(pair)?;
+            // A broken pattern is caught right here instead of
+            // panicking the first time the struct is validated
+            if let Err(error) = regex::Regex::new(&pattern.value()) {
+                return Err(syn::Error::new_spanned(pattern, error));
+            }
+            Check::Regex(pattern)
+        }
+        syn::Meta::NameValue(ref pair) if pair.path.is_ident("custom") => {
+            Check::Custom(attributes::string_literal(pair)?.parse()?)
+        }
+        syn::Meta::List(ref list) if list.path.is_ident("range") || list.path.is_ident("length") => {
+            let (mut min, mut max) = (None, None);
+            for nested in &list.nested {
+                match nested {
+                    syn::NestedMeta::Meta(syn::Meta::NameValue(pair)) if pair.path.is_ident("min") => {
+                        min = Some(pair.lit.clone())
+                    }
+                    syn::NestedMeta::Meta(syn::Meta::NameValue(pair)) if pair.path.is_ident("max") => {
+                        max = Some(pair.lit.clone())
+                    }
+                    other => {
+                        return Err(syn::Error::new_spanned(other, "Expected min = ... or max = ..."))
+                    }
+                }
+            }
+            if min.is_none() && max.is_none() {
+                return Err(syn::Error::new_spanned(list, "Expected at least one of min and max"));
+            }
+            if list.path.is_ident("range") {
+                Check::Range { min, max }
+            } else {
+                Check::Length { min, max }
+            }
+        }
+        other => {
+            return Err(syn::Error::new_spanned(
+                other,
+                "Expected one of range(...), length(...), regex = \"...\", email, nested or custom = \"function\"",
+            ))
+        }
+    };
+    Ok(check)
+}
+
+// Builds the code for a single check, which finds the field in `value`
+fn check_tokens(check: &Check, field_type: &syn::Type, name: &str) -> proc_macro2::TokenStream {
+    match *check {
+        Check::Range { ref min, ref max } => {
+            let min = min.iter();
+            let max = max.iter();
+            quote! {
+                #(
+                    if *value < #min {
+                        errors.field(#name).add(format!("must be at least {}", #min));
+                    }
+                )*
+                #(
+                    if *value > #max {
+                        errors.field(#name).add(format!("must be at most {}", #max));
+                    }
+                )*
+            }
+        }
+        Check::Length { ref min, ref max } => {
+            let min = min.iter();
+            let max = max.iter();
+            // Strings are measured in characters, so "Zoë" has a length of 3
+            let length = if is_type(field_type, "String") {
+                quote! { value.chars().count() }
+            } else {
+                quote! { value.len() }
+            };
+            quote! {
+                #(
+                    if #length < #min {
+                        errors.field(#name).add(format!("must have a length of at least {}", #min));
+                    }
+                )*
+                #(
+                    if #length > #max {
+                        errors.field(#name).add(format!("must have a length of at most {}", #max));
+                    }
+                )*
+            }
+        }
+        Check::Regex(ref pattern) => regex_tokens(
+            quote! { #pattern },
+            name,
+            quote! { format!("must match {}", #pattern) },
+        ),
+        Check::Email => regex_tokens(
+            quote! { #EMAIL_PATTERN },
+            name,
+            quote! { "must be a valid email address".to_string() },
+        ),
+        // Vecs are validated element by element, with the index as part of the path
+        Check::Nested => match generic_argument(field_type, "Vec") {
+            Some(_) => quote! {
+                for (index, element) in value.iter().enumerate() {
+                    Validate::validate_into(element, errors.field(#name).field(&index.to_string()));
+                }
+            },
+            None => quote! {
+                Validate::validate_into(value, errors.field(#name));
+            },
+        },
+        Check::Custom(ref function) => quote! {
+            if let ::std::result::Result::Err(message) = #function(value) {
+                errors.field(#name).add(message);
+            }
+        },
+    }
+}
+
+fn regex_tokens(
+    pattern: proc_macro2::TokenStream,
+    name: &str,
+    message: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    // Every check gets its own block, and with it its own static,
+    // so the regex is compiled only once, just like in lazy_static.rs
+    quote! {
+        {
+            ::lazy_static::lazy_static! {
+                static ref RE: ::regex::Regex =
+                    ::regex::Regex::new(#pattern).expect("Failed to create regex");
+            }
+            if !RE.is_match(value) {
+                errors.field(#name).add(#message);
+            }
+        }
+    }
+}
+
+fn is_type(ty: &syn::Type, name: &str) -> bool {
+    match *ty {
+        syn::Type::Path(ref path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == name),
+        _ => false,
+    }
+}
+
+// Returns T for types like Option<T> or std::vec::Vec<T>
+fn generic_argument<'a>(ty: &'a syn::Type, name: &str) -> Option<&'a syn::Type> {
+    if !is_type(ty, name) {
+        return None;
+    }
+    let segment = match *ty {
+        syn::Type::Path(ref path) => path.path.segments.last()?,
+        _ => return None,
+    };
+    match segment.arguments {
+        syn::PathArguments::AngleBracketed(ref arguments) => {
+            arguments.args.iter().find_map(|argument| match argument {
+                syn::GenericArgument::Type(ty) => Some(ty),
+                _ => None,
+            })
+        }
+        _ => None,
+    }
+}
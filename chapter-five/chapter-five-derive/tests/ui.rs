@@ -0,0 +1,9 @@
+// Every file in ui/pass has to compile, every file in ui/fail has to fail
+// with exactly the error in the .stderr file next to it.
+// Run with TRYBUILD=overwrite to update the .stderr files after changing a message
+#[test]
+fn ui() {
+    let tests = trybuild::TestCases::new();
+    tests.pass("tests/ui/pass/*.rs");
+    tests.compile_fail("tests/ui/fail/*.rs");
+}
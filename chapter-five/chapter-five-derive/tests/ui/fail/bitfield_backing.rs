@@ -0,0 +1,9 @@
+use chapter_five_derive::bitfield;
+
+bitfield! {
+    struct Header: i32 {
+        version: u32,
+    }
+}
+
+fn main() {}
@@ -0,0 +1,14 @@
+use chapter_five_derive::bitfield;
+
+#[derive(Debug)]
+enum Protocol {
+    Tcp,
+}
+
+bitfield! {
+    struct Header: u8 {
+        protocol: Protocol,
+    }
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use chapter_five_derive::bitfield;
+
+bitfield! {
+    struct Offset: u8 {
+        #[bits(8)]
+        offset: i8,
+    }
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use chapter_five_derive::bitfield;
+
+bitfield! {
+    struct Header: u8 {
+        #[bits(4)]
+        version: u8,
+        #[bits(5)]
+        length: u8,
+    }
+}
+
+fn main() {}
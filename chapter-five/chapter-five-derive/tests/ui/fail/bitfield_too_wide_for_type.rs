@@ -0,0 +1,12 @@
+use chapter_five_derive::bitfield;
+
+bitfield! {
+    struct Header: u16 {
+        #[bits(12)]
+        version: u8,
+        #[bits(4)]
+        length: u8,
+    }
+}
+
+fn main() {}
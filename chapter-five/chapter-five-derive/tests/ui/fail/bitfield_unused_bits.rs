@@ -0,0 +1,10 @@
+use chapter_five_derive::bitfield;
+
+bitfield! {
+    struct Header: u16 {
+        #[bits(4)]
+        version: u8,
+    }
+}
+
+fn main() {}
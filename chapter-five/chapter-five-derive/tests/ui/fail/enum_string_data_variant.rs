@@ -0,0 +1,9 @@
+use chapter_five_derive::EnumString;
+
+#[derive(EnumString)]
+enum Species {
+    Dog,
+    Other(String),
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use chapter_five_derive::EnumString;
+
+#[derive(EnumString)]
+#[enum_string(case_insensitive)]
+enum Species {
+    #[enum_string(rename = "dog")]
+    Dog,
+    #[enum_string(alias = "DOG")]
+    Wolf,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use chapter_five_derive::EnumString;
+
+#[derive(EnumString)]
+enum Species {
+    #[enum_string(rename = 5)]
+    Dog,
+}
+
+fn main() {}
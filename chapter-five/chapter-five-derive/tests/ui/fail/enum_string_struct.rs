@@ -0,0 +1,6 @@
+use chapter_five_derive::EnumString;
+
+#[derive(EnumString)]
+struct Dog;
+
+fn main() {}
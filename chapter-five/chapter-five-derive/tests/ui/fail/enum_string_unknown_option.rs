@@ -0,0 +1,9 @@
+use chapter_five_derive::EnumString;
+
+#[derive(EnumString)]
+#[enum_string(ignore_case)]
+enum Species {
+    Dog,
+}
+
+fn main() {}
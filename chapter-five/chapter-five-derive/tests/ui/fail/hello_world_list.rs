@@ -0,0 +1,12 @@
+use chapter_five_derive::HelloWorld;
+
+trait HelloWorld {
+    fn hello_world();
+    fn hello_world_name(&self) -> &'static str;
+}
+
+#[derive(HelloWorld)]
+#[hello_world_name("Narnia")]
+struct Narnia;
+
+fn main() {}
@@ -0,0 +1,14 @@
+use chapter_five_derive::HelloWorld;
+
+trait HelloWorld {
+    fn hello_world();
+    fn hello_world_name(&self) -> &'static str;
+}
+
+#[derive(HelloWorld)]
+struct Narnia {
+    #[hello_world_name = "the king"]
+    king: String,
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use chapter_five_derive::HelloWorld;
+
+trait HelloWorld {
+    fn hello_world();
+    fn hello_world_name(&self) -> &'static str;
+}
+
+#[derive(HelloWorld)]
+enum Fiction {
+    Narnia(#[hello_world_name = "the king"] String),
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use chapter_five_derive::HelloWorld;
+
+trait HelloWorld {
+    fn hello_world();
+    fn hello_world_name(&self) -> &'static str;
+}
+
+#[derive(HelloWorld)]
+#[hello_world_name = "Narnia"]
+#[hello_world_name = "Neverland"]
+struct Narnia;
+
+fn main() {}
@@ -0,0 +1,29 @@
+use chapter_five_derive::Validate;
+
+trait Validate {
+    fn validate_into(&self, errors: &mut ValidationErrors);
+}
+
+#[derive(Default)]
+struct ValidationErrors {
+    messages: Vec<String>,
+    fields: std::collections::BTreeMap<String, ValidationErrors>,
+}
+
+impl ValidationErrors {
+    fn add(&mut self, message: String) {
+        self.messages.push(message);
+    }
+
+    fn field(&mut self, name: &str) -> &mut ValidationErrors {
+        self.fields.entry(name.to_string()).or_default()
+    }
+}
+
+#[derive(Validate)]
+struct Pet {
+    #[validate(range())]
+    age: i32,
+}
+
+fn main() {}
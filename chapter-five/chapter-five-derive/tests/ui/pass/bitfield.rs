@@ -0,0 +1,37 @@
+use chapter_five_derive::bitfield;
+
+bitfield! {
+    #[derive(Clone, Copy, PartialEq, Eq, Default)]
+    pub struct Whole: u64 {
+        value: u64,
+    }
+}
+
+bitfield! {
+    pub struct Nibbles: u8 {
+        /// The most significant half
+        #[bits(4)]
+        high: u8,
+        #[bits(3)]
+        _reserved: u8,
+        flag: bool,
+    }
+}
+
+fn main() {
+    let mut whole = Whole::default();
+    whole.set_value(u64::MAX).unwrap();
+    assert_eq!(whole.value(), u64::MAX);
+    assert_eq!(Whole::from_be_bytes(whole.to_be_bytes()), whole);
+
+    let mut nibbles = Nibbles::from_bits(0b1010_1110);
+    assert_eq!(nibbles.high(), 0b1010);
+    assert!(!nibbles.flag());
+    nibbles.set_flag(true);
+    assert!(nibbles.set_high(16).is_err());
+    nibbles.set_high(0b0101).unwrap();
+    // The reserved bits that came in are cleared
+    assert_eq!(nibbles.bits(), 0b0101_0001);
+    assert_eq!(Nibbles::from_be_bytes([0b0000_1110]).bits(), 0);
+    assert_eq!(format!("{:?}", nibbles), "Nibbles { high: 5, flag: true }");
+}
@@ -0,0 +1,19 @@
+use chapter_five_derive::EnumString;
+
+#[derive(Debug, PartialEq, EnumString)]
+#[enum_string(case_insensitive)]
+enum Species {
+    #[enum_string(rename = "dog", alias = "puppy")]
+    Dog,
+    Cat,
+}
+
+fn main() {
+    assert_eq!("PUPPY".parse(), Ok(Species::Dog));
+    assert_eq!("cat".parse(), Ok(Species::Cat));
+    assert!("horse".parse::<Species>().is_err());
+    assert_eq!(Species::Dog.to_string(), "dog");
+    assert_eq!(Species::iter().collect::<Vec<_>>(), [Species::Dog, Species::Cat]);
+    const COUNT: usize = Species::variant_count();
+    assert_eq!(COUNT, 2);
+}
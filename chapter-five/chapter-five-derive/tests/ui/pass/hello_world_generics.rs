@@ -0,0 +1,36 @@
+use chapter_five_derive::HelloWorld;
+use std::fmt::Debug;
+
+trait HelloWorld {
+    fn hello_world();
+    fn hello_world_name(&self) -> &'static str;
+}
+
+#[derive(HelloWorld)]
+struct Wrapper<T>(T);
+
+#[derive(HelloWorld)]
+#[hello_world_name = "a borrowed list"]
+struct Borrowed<'a, T: Debug, const N: usize>
+where
+    T: Clone,
+{
+    items: &'a [T; N],
+}
+
+#[derive(HelloWorld)]
+enum Either<L, R> {
+    #[hello_world_name = "the left side"]
+    Left(L),
+    Right { value: R },
+}
+
+fn main() {
+    Wrapper::<u8>::hello_world();
+    Borrowed::<u8, 2>::hello_world();
+    assert_eq!(Wrapper(1).hello_world_name(), "Wrapper");
+    let items = [1, 2];
+    assert_eq!(Borrowed { items: &items }.hello_world_name(), "a borrowed list");
+    assert_eq!(Either::<u8, u8>::Left(1).hello_world_name(), "the left side");
+    assert_eq!(Either::<u8, u8>::Right { value: 2 }.hello_world_name(), "Right");
+}
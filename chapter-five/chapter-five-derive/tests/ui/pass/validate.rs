@@ -0,0 +1,45 @@
+use chapter_five_derive::Validate;
+
+trait Validate {
+    fn validate_into(&self, errors: &mut ValidationErrors);
+}
+
+#[derive(Default)]
+struct ValidationErrors {
+    messages: Vec<String>,
+    fields: std::collections::BTreeMap<String, ValidationErrors>,
+}
+
+impl ValidationErrors {
+    fn add(&mut self, message: String) {
+        self.messages.push(message);
+    }
+
+    fn field(&mut self, name: &str) -> &mut ValidationErrors {
+        self.fields.entry(name.to_string()).or_default()
+    }
+}
+
+#[derive(Validate)]
+struct Point(#[validate(range(min = 0.0))] f64, #[validate(range(max = 10))] u8);
+
+#[derive(Validate)]
+struct Polygon<T: Validate> {
+    #[validate(length(min = 3), nested)]
+    corners: Vec<T>,
+    #[validate(regex = "^[a-z]+$")]
+    r#name: Option<String>,
+}
+
+fn main() {
+    let polygon = Polygon {
+        corners: vec![Point(-1.0, 1), Point(0.0, 11)],
+        r#name: Some("Triangle".to_string()),
+    };
+    let mut errors = ValidationErrors::default();
+    polygon.validate_into(&mut errors);
+    assert_eq!(errors.fields["corners"].messages, ["must have a length of at least 3"]);
+    assert_eq!(errors.fields["corners"].fields["0"].fields["0"].messages, ["must be at least 0"]);
+    assert_eq!(errors.fields["corners"].fields["1"].fields["1"].messages, ["must be at most 10"]);
+    assert_eq!(errors.fields["name"].messages, ["must match ^[a-z]+$"]);
+}
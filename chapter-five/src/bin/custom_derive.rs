@@ -4,6 +4,8 @@ use chapter_five_derive::HelloWorld;
 trait HelloWorld {
     // This method will send a friendly greeting
     fn hello_world();
+    // The name used in the greeting, enums have one per variant
+    fn hello_world_name(&self) -> &'static str;
 }
 
 // thanks to the code in the custom_derive crate
@@ -20,8 +22,40 @@ struct Britain;
 #[hello_world_name = "the Land Down Under"]
 struct Australia;
 
+// Generic types work as well, including their bounds
+#[derive(HelloWorld)]
+#[hello_world_name = "a box of anything printable"]
+struct Package<T>
+where
+    T: std::fmt::Display,
+{
+    content: T,
+}
+
+// Every variant can have its own name
+#[derive(HelloWorld)]
+enum Country {
+    #[hello_world_name = "the Confoederatio Helvetica"]
+    Switzerland,
+    Britain,
+    #[hello_world_name = "the Land Down Under"]
+    Australia { states: u8 },
+}
+
 fn main() {
     Switzerland::hello_world();
     Britain::hello_world();
     Australia::hello_world();
+    Package::<String>::hello_world();
+    Country::hello_world();
+
+    let package = Package { content: "Chocolate" };
+    println!("{} contains {}", package.hello_world_name(), package.content);
+    let countries = [Country::Switzerland, Country::Britain, Country::Australia { states: 6 }];
+    for country in &countries {
+        println!("Greetings to {}", country.hello_world_name());
+        if let Country::Australia { states } = country {
+            println!("and all of its {} states", states);
+        }
+    }
 }
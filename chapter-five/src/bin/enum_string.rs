@@ -0,0 +1,53 @@
+use chapter_five_derive::EnumString;
+use std::env;
+
+// The same species as in the JSON recipe of chapter four,
+// but now they can be printed and parsed without {:?}
+#[derive(Debug, PartialEq, EnumString)]
+#[enum_string(case_insensitive)]
+enum AllowedSpecies {
+    #[enum_string(rename = "dog", alias = "puppy")]
+    Dog,
+    #[enum_string(rename = "turtle", alias = "tortoise")]
+    Turtle,
+    #[enum_string(rename = "cat", alias = "kitten")]
+    Cat,
+}
+
+// Without attributes, the variants are printed and parsed as they are written
+#[derive(Debug, EnumString)]
+enum Colour {
+    Red,
+    Green,
+    Blue,
+}
+
+fn main() {
+    println!("There are {} allowed species:", AllowedSpecies::variant_count());
+    for species in AllowedSpecies::iter() {
+        println!("- {:<8}(debug: {:?})", species, species);
+    }
+
+    // Aliases and different casing are fine
+    for input in &["dog", "Puppy", "TORTOISE", "Cat"] {
+        let species: AllowedSpecies = input.parse().expect("Failed to parse species");
+        println!("'{}' is a {}", input, species);
+    }
+    // Colour is not case insensitive
+    println!("'Red' as colour: {:?}", "Red".parse::<Colour>());
+    println!("'red' as colour: {:?}", "red".parse::<Colour>());
+    let colours: Vec<String> = Colour::iter().map(|colour| colour.to_string()).collect();
+    println!("All colours: {}", colours.join(", "));
+
+    // A typical use: a command line flag like --species dog
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--species" {
+            match args.next().map(|species| species.parse::<AllowedSpecies>()) {
+                Some(Ok(species)) => println!("Looking for a {}", species),
+                Some(Err(e)) => println!("{}", e),
+                None => println!("--species needs a value"),
+            }
+        }
+    }
+}
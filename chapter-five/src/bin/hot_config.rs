@@ -0,0 +1,192 @@
+use arc_swap::ArcSwap;
+use lazy_static::lazy_static;
+use serde_derive::Deserialize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use std::{env, fs};
+
+#[derive(Debug, Default, PartialEq, Deserialize)]
+struct Config {
+    clients: Vec<String>,
+    max_connections: u32,
+    greeting: String,
+}
+
+// Unlike CLIENTS in lazy_static.rs, readers never lock anything
+lazy_static! {
+    static ref CONFIG: ConfigCell<Config> = ConfigCell::new(Config::default());
+}
+
+// Holds the current version of a value. Readers get an Arc to it,
+// which stays valid and unchanged for as long as they keep it,
+// even while writers swap in newer versions
+struct ConfigCell<T> {
+    current: ArcSwap<T>,
+    // Only touched when writing or subscribing, never when reading
+    subscribers: Mutex<Vec<Sender<Arc<T>>>>,
+}
+
+impl<T> ConfigCell<T> {
+    fn new(value: T) -> Self {
+        ConfigCell {
+            current: ArcSwap::from_pointee(value),
+            subscribers: Mutex::new(Vec::new()),
+        }
+    }
+
+    // As cheap as cloning an Arc
+    fn get(&self) -> Arc<T> {
+        self.current.load_full()
+    }
+
+    // Replaces the value all at once, readers see either
+    // the old or the new version, but never a mix of both
+    fn set(&self, value: T) {
+        let value = Arc::new(value);
+        // Writers are serialized by the lock, so subscribers
+        // get the versions in the same order they were set
+        let mut subscribers = self
+            .subscribers
+            .lock()
+            .expect("Failed to lock subscribers");
+        self.current.store(Arc::clone(&value));
+        // Subscribers that dropped their receiver are forgotten
+        subscribers.retain(|subscriber| subscriber.send(Arc::clone(&value)).is_ok());
+    }
+
+    // Every version set from now on is sent to the receiver
+    fn subscribe(&self) -> Receiver<Arc<T>> {
+        let (tx, rx) = channel();
+        self.subscribers
+            .lock()
+            .expect("Failed to lock subscribers")
+            .push(tx);
+        rx
+    }
+
+    // Ends the iteration of every subscriber, e.g. when shutting down
+    fn close(&self) {
+        self.subscribers
+            .lock()
+            .expect("Failed to lock subscribers")
+            .clear();
+    }
+}
+
+fn load(path: &Path) -> Result<Config, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    toml::from_str(&content).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+}
+
+// Reloads the config whenever the file changes, until it is dropped
+struct Watcher {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Watcher {
+    // Polls the file, which works the same on every platform and file system.
+    // Comparing the content instead of the modification time means that
+    // two changes within the same second are never missed
+    fn start(path: PathBuf, interval: Duration) -> Watcher {
+        let stop = Arc::new(AtomicBool::new(false));
+        let should_stop = Arc::clone(&stop);
+        let handle = thread::spawn(move || {
+            let mut last_content = None;
+            while !should_stop.load(Ordering::SeqCst) {
+                let content = fs::read_to_string(&path).ok();
+                if content.is_some() && content != last_content {
+                    last_content = content;
+                    // A broken file keeps the old config running,
+                    // so a typo can't take down the service
+                    match load(&path) {
+                        Ok(config) => CONFIG.set(config),
+                        Err(e) => eprintln!("Keeping the current config: {}", e),
+                    }
+                }
+                thread::sleep(interval);
+            }
+        });
+        Watcher {
+            stop,
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            handle.join().expect("The watcher thread panicked");
+        }
+    }
+}
+
+const INTERVAL: Duration = Duration::from_millis(50);
+
+fn main() {
+    let path = env::temp_dir().join("hot_config.toml");
+    fs::write(
+        &path,
+        "clients = [\"192.168.0.1\"]\nmax_connections = 10\ngreeting = \"Hello\"\n",
+    )
+    .expect("Failed to write config");
+
+    // Subscribers run in their own thread and react to every change
+    let changes = CONFIG.subscribe();
+    let subscriber = thread::spawn(move || {
+        for config in changes {
+            println!("New config: {:?}", config);
+        }
+    });
+
+    let watcher = Watcher::start(path.clone(), INTERVAL);
+    thread::sleep(INTERVAL * 4);
+
+    // A reader keeps using its snapshot, no matter what happens to the file
+    let snapshot = CONFIG.get();
+    let readers: Vec<_> = (0..4)
+        .map(|id| {
+            thread::spawn(move || {
+                for _ in 0..3 {
+                    let config = CONFIG.get();
+                    println!("Reader {}: {} to {} clients", id, config.greeting, config.clients.len());
+                    thread::sleep(INTERVAL * 2);
+                }
+            })
+        })
+        .collect();
+
+    // The service keeps running while its settings change
+    fs::write(
+        &path,
+        "clients = [\"192.168.0.1\", \"192.168.0.2\"]\nmax_connections = 20\ngreeting = \"Howdy\"\n",
+    )
+    .expect("Failed to write config");
+    thread::sleep(INTERVAL * 4);
+    fs::write(&path, "clients = [\"192.168.0.1\"\nmax_connections = \"many\"\n")
+        .expect("Failed to write config");
+    thread::sleep(INTERVAL * 4);
+
+    for reader in readers {
+        reader.join().expect("A reader panicked");
+    }
+    println!("The old snapshot still says: {}", snapshot.greeting);
+    println!("The current config says: {}", CONFIG.get().greeting);
+
+    // Setting the config by hand works just as well
+    drop(watcher);
+    CONFIG.set(Config {
+        clients: Vec::new(),
+        max_connections: 0,
+        greeting: "Goodbye".to_string(),
+    });
+    // Nothing can change the config anymore, so the subscriber can stop
+    CONFIG.close();
+    subscriber.join().expect("The subscriber panicked");
+}
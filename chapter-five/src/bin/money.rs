@@ -0,0 +1,554 @@
+use lazy_static::lazy_static;
+use rust_decimal::{Decimal, RoundingStrategy};
+use std::collections::HashMap;
+use std::{error, fmt, result, str};
+
+#[derive(Debug, PartialEq)]
+struct Currency {
+    code: &'static str,
+    number: u16,
+    // How many digits come after the decimal point, 2 for cents.
+    // Precious metals and the like have no minor unit at all
+    minor_units: Option<u32>,
+    name: &'static str,
+}
+
+// The active codes of ISO 4217
+#[rustfmt::skip]
+const ISO_4217: &[(&str, u16, Option<u32>, &str)] = &[
+    ("AED", 784, Some(2), "UAE Dirham"),
+    ("AFN", 971, Some(2), "Afghani"),
+    ("ALL", 8, Some(2), "Lek"),
+    ("AMD", 51, Some(2), "Armenian Dram"),
+    ("AOA", 973, Some(2), "Kwanza"),
+    ("ARS", 32, Some(2), "Argentine Peso"),
+    ("AUD", 36, Some(2), "Australian Dollar"),
+    ("AWG", 533, Some(2), "Aruban Florin"),
+    ("AZN", 944, Some(2), "Azerbaijan Manat"),
+    ("BAM", 977, Some(2), "Convertible Mark"),
+    ("BBD", 52, Some(2), "Barbados Dollar"),
+    ("BDT", 50, Some(2), "Taka"),
+    ("BHD", 48, Some(3), "Bahraini Dinar"),
+    ("BIF", 108, Some(0), "Burundi Franc"),
+    ("BMD", 60, Some(2), "Bermudian Dollar"),
+    ("BND", 96, Some(2), "Brunei Dollar"),
+    ("BOB", 68, Some(2), "Boliviano"),
+    ("BOV", 984, Some(2), "Mvdol"),
+    ("BRL", 986, Some(2), "Brazilian Real"),
+    ("BSD", 44, Some(2), "Bahamian Dollar"),
+    ("BTN", 64, Some(2), "Ngultrum"),
+    ("BWP", 72, Some(2), "Pula"),
+    ("BYN", 933, Some(2), "Belarusian Ruble"),
+    ("BZD", 84, Some(2), "Belize Dollar"),
+    ("CAD", 124, Some(2), "Canadian Dollar"),
+    ("CDF", 976, Some(2), "Congolese Franc"),
+    ("CHE", 947, Some(2), "WIR Euro"),
+    ("CHF", 756, Some(2), "Swiss Franc"),
+    ("CHW", 948, Some(2), "WIR Franc"),
+    ("CLF", 990, Some(4), "Unidad de Fomento"),
+    ("CLP", 152, Some(0), "Chilean Peso"),
+    ("CNY", 156, Some(2), "Yuan Renminbi"),
+    ("COP", 170, Some(2), "Colombian Peso"),
+    ("COU", 970, Some(2), "Unidad de Valor Real"),
+    ("CRC", 188, Some(2), "Costa Rican Colon"),
+    ("CUP", 192, Some(2), "Cuban Peso"),
+    ("CVE", 132, Some(2), "Cabo Verde Escudo"),
+    ("CZK", 203, Some(2), "Czech Koruna"),
+    ("DJF", 262, Some(0), "Djibouti Franc"),
+    ("DKK", 208, Some(2), "Danish Krone"),
+    ("DOP", 214, Some(2), "Dominican Peso"),
+    ("DZD", 12, Some(2), "Algerian Dinar"),
+    ("EGP", 818, Some(2), "Egyptian Pound"),
+    ("ERN", 232, Some(2), "Nakfa"),
+    ("ETB", 230, Some(2), "Ethiopian Birr"),
+    ("EUR", 978, Some(2), "Euro"),
+    ("FJD", 242, Some(2), "Fiji Dollar"),
+    ("FKP", 238, Some(2), "Falkland Islands Pound"),
+    ("GBP", 826, Some(2), "Pound Sterling"),
+    ("GEL", 981, Some(2), "Lari"),
+    ("GHS", 936, Some(2), "Ghana Cedi"),
+    ("GIP", 292, Some(2), "Gibraltar Pound"),
+    ("GMD", 270, Some(2), "Dalasi"),
+    ("GNF", 324, Some(0), "Guinean Franc"),
+    ("GTQ", 320, Some(2), "Quetzal"),
+    ("GYD", 328, Some(2), "Guyana Dollar"),
+    ("HKD", 344, Some(2), "Hong Kong Dollar"),
+    ("HNL", 340, Some(2), "Lempira"),
+    ("HTG", 332, Some(2), "Gourde"),
+    ("HUF", 348, Some(2), "Forint"),
+    ("IDR", 360, Some(2), "Rupiah"),
+    ("ILS", 376, Some(2), "New Israeli Sheqel"),
+    ("INR", 356, Some(2), "Indian Rupee"),
+    ("IQD", 368, Some(3), "Iraqi Dinar"),
+    ("IRR", 364, Some(2), "Iranian Rial"),
+    ("ISK", 352, Some(0), "Iceland Krona"),
+    ("JMD", 388, Some(2), "Jamaican Dollar"),
+    ("JOD", 400, Some(3), "Jordanian Dinar"),
+    ("JPY", 392, Some(0), "Yen"),
+    ("KES", 404, Some(2), "Kenyan Shilling"),
+    ("KGS", 417, Some(2), "Som"),
+    ("KHR", 116, Some(2), "Riel"),
+    ("KMF", 174, Some(0), "Comorian Franc"),
+    ("KPW", 408, Some(2), "North Korean Won"),
+    ("KRW", 410, Some(0), "Won"),
+    ("KWD", 414, Some(3), "Kuwaiti Dinar"),
+    ("KYD", 136, Some(2), "Cayman Islands Dollar"),
+    ("KZT", 398, Some(2), "Tenge"),
+    ("LAK", 418, Some(2), "Lao Kip"),
+    ("LBP", 422, Some(2), "Lebanese Pound"),
+    ("LKR", 144, Some(2), "Sri Lanka Rupee"),
+    ("LRD", 430, Some(2), "Liberian Dollar"),
+    ("LSL", 426, Some(2), "Loti"),
+    ("LYD", 434, Some(3), "Libyan Dinar"),
+    ("MAD", 504, Some(2), "Moroccan Dirham"),
+    ("MDL", 498, Some(2), "Moldovan Leu"),
+    ("MGA", 969, Some(2), "Malagasy Ariary"),
+    ("MKD", 807, Some(2), "Denar"),
+    ("MMK", 104, Some(2), "Kyat"),
+    ("MNT", 496, Some(2), "Tugrik"),
+    ("MOP", 446, Some(2), "Pataca"),
+    ("MRU", 929, Some(2), "Ouguiya"),
+    ("MUR", 480, Some(2), "Mauritius Rupee"),
+    ("MVR", 462, Some(2), "Rufiyaa"),
+    ("MWK", 454, Some(2), "Malawi Kwacha"),
+    ("MXN", 484, Some(2), "Mexican Peso"),
+    ("MXV", 979, Some(2), "Mexican Unidad de Inversion (UDI)"),
+    ("MYR", 458, Some(2), "Malaysian Ringgit"),
+    ("MZN", 943, Some(2), "Mozambique Metical"),
+    ("NAD", 516, Some(2), "Namibia Dollar"),
+    ("NGN", 566, Some(2), "Naira"),
+    ("NIO", 558, Some(2), "Cordoba Oro"),
+    ("NOK", 578, Some(2), "Norwegian Krone"),
+    ("NPR", 524, Some(2), "Nepalese Rupee"),
+    ("NZD", 554, Some(2), "New Zealand Dollar"),
+    ("OMR", 512, Some(3), "Rial Omani"),
+    ("PAB", 590, Some(2), "Balboa"),
+    ("PEN", 604, Some(2), "Sol"),
+    ("PGK", 598, Some(2), "Kina"),
+    ("PHP", 608, Some(2), "Philippine Peso"),
+    ("PKR", 586, Some(2), "Pakistan Rupee"),
+    ("PLN", 985, Some(2), "Zloty"),
+    ("PYG", 600, Some(0), "Guarani"),
+    ("QAR", 634, Some(2), "Qatari Rial"),
+    ("RON", 946, Some(2), "Romanian Leu"),
+    ("RSD", 941, Some(2), "Serbian Dinar"),
+    ("RUB", 643, Some(2), "Russian Ruble"),
+    ("RWF", 646, Some(0), "Rwanda Franc"),
+    ("SAR", 682, Some(2), "Saudi Riyal"),
+    ("SBD", 90, Some(2), "Solomon Islands Dollar"),
+    ("SCR", 690, Some(2), "Seychelles Rupee"),
+    ("SDG", 938, Some(2), "Sudanese Pound"),
+    ("SEK", 752, Some(2), "Swedish Krona"),
+    ("SGD", 702, Some(2), "Singapore Dollar"),
+    ("SHP", 654, Some(2), "Saint Helena Pound"),
+    ("SLE", 925, Some(2), "Leone"),
+    ("SOS", 706, Some(2), "Somali Shilling"),
+    ("SRD", 968, Some(2), "Surinam Dollar"),
+    ("SSP", 728, Some(2), "South Sudanese Pound"),
+    ("STN", 930, Some(2), "Dobra"),
+    ("SVC", 222, Some(2), "El Salvador Colon"),
+    ("SYP", 760, Some(2), "Syrian Pound"),
+    ("SZL", 748, Some(2), "Lilangeni"),
+    ("THB", 764, Some(2), "Baht"),
+    ("TJS", 972, Some(2), "Somoni"),
+    ("TMT", 934, Some(2), "Turkmenistan New Manat"),
+    ("TND", 788, Some(3), "Tunisian Dinar"),
+    ("TOP", 776, Some(2), "Pa'anga"),
+    ("TRY", 949, Some(2), "Turkish Lira"),
+    ("TTD", 780, Some(2), "Trinidad and Tobago Dollar"),
+    ("TWD", 901, Some(2), "New Taiwan Dollar"),
+    ("TZS", 834, Some(2), "Tanzanian Shilling"),
+    ("UAH", 980, Some(2), "Hryvnia"),
+    ("UGX", 800, Some(0), "Uganda Shilling"),
+    ("USD", 840, Some(2), "US Dollar"),
+    ("USN", 997, Some(2), "US Dollar (Next day)"),
+    ("UYI", 940, Some(0), "Uruguay Peso en Unidades Indexadas (UI)"),
+    ("UYU", 858, Some(2), "Peso Uruguayo"),
+    ("UYW", 927, Some(4), "Unidad Previsional"),
+    ("UZS", 860, Some(2), "Uzbekistan Sum"),
+    ("VED", 926, Some(2), "Bolívar Soberano"),
+    ("VES", 928, Some(2), "Bolívar Soberano"),
+    ("VND", 704, Some(0), "Dong"),
+    ("VUV", 548, Some(0), "Vatu"),
+    ("WST", 882, Some(2), "Tala"),
+    ("XAF", 950, Some(0), "CFA Franc BEAC"),
+    ("XAG", 961, None, "Silver"),
+    ("XAU", 959, None, "Gold"),
+    ("XCD", 951, Some(2), "East Caribbean Dollar"),
+    ("XCG", 532, Some(2), "Caribbean Guilder"),
+    ("XDR", 960, None, "SDR (Special Drawing Right)"),
+    ("XOF", 952, Some(0), "CFA Franc BCEAO"),
+    ("XPD", 964, None, "Palladium"),
+    ("XPF", 953, Some(0), "CFP Franc"),
+    ("XPT", 962, None, "Platinum"),
+    ("XXX", 999, None, "No currency"),
+    ("YER", 886, Some(2), "Yemeni Rial"),
+    ("ZAR", 710, Some(2), "Rand"),
+    ("ZMW", 967, Some(2), "Zambian Kwacha"),
+    ("ZWG", 924, Some(2), "Zimbabwe Gold"),
+];
+
+// Built on first use, just like CURRENCIES in lazy_static.rs
+lazy_static! {
+    static ref CURRENCIES: HashMap<&'static str, Currency> = ISO_4217
+        .iter()
+        .map(|&(code, number, minor_units, name)| {
+            let currency = Currency {
+                code,
+                number,
+                minor_units,
+                name,
+            };
+            (code, currency)
+        })
+        .collect();
+}
+
+fn currency(code: &str) -> Result<&'static Currency> {
+    CURRENCIES
+        .get(code)
+        .ok_or_else(|| MoneyError::UnknownCurrency(code.to_string()))
+}
+
+// Our custom error, following the pattern from chapter six
+#[derive(Debug, PartialEq)]
+enum MoneyError {
+    UnknownCurrency(String),
+    CurrencyMismatch(&'static str, &'static str),
+    // Like 1.005 USD, as there are no tenths of a cent
+    TooPrecise(Decimal, &'static str),
+    Parse(String),
+    MissingRate(&'static str, &'static str),
+    Overflow,
+    Allocation(String),
+}
+
+type Result<T> = result::Result<T, MoneyError>;
+
+impl error::Error for MoneyError {
+    fn cause(&self) -> Option<&dyn error::Error> {
+        None
+    }
+}
+
+impl fmt::Display for MoneyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MoneyError::UnknownCurrency(ref code) => write!(f, "'{}' is not an ISO 4217 currency", code),
+            MoneyError::CurrencyMismatch(a, b) => write!(f, "Can't combine {} with {}", a, b),
+            MoneyError::TooPrecise(amount, code) => {
+                write!(f, "{} has more decimal places than {} allows", amount, code)
+            }
+            MoneyError::Parse(ref message) => write!(f, "Parse error: {}", message),
+            MoneyError::MissingRate(from, to) => write!(f, "No rate from {} to {}", from, to),
+            MoneyError::Overflow => write!(f, "The amount is too large"),
+            MoneyError::Allocation(ref message) => write!(f, "Can't allocate: {}", message),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Money {
+    // Always stored with exactly as many decimal places as the currency has
+    amount: Decimal,
+    currency: &'static Currency,
+}
+
+impl Money {
+    // Rejects amounts that can't be paid, like 0.001 EUR
+    fn new(amount: Decimal, code: &str) -> Result<Money> {
+        let currency = currency(code)?;
+        if let Some(minor_units) = currency.minor_units {
+            if amount.round_dp(minor_units) != amount {
+                return Err(MoneyError::TooPrecise(amount, currency.code));
+            }
+        }
+        Money::rounded(amount, currency, RoundingStrategy::ToZero)
+    }
+
+    // Counts in cents, or whatever the smallest unit of the currency is
+    fn from_minor(minor: i64, code: &str) -> Result<Money> {
+        let currency = currency(code)?;
+        let minor_units = currency.minor_units.unwrap_or(0);
+        Money::new(Decimal::new(minor, minor_units), code)
+    }
+
+    fn rounded(amount: Decimal, currency: &'static Currency, rounding: RoundingStrategy) -> Result<Money> {
+        let amount = match currency.minor_units {
+            Some(minor_units) => {
+                let mut amount = amount.round_dp_with_strategy(minor_units, rounding);
+                // Pads 12.5 to 12.50, so it's printed like a price
+                amount.rescale(minor_units);
+                // Amounts that use up all 28 digits have no room left for
+                // the padding, and rescale() quietly keeps fewer places
+                if amount.scale() != minor_units {
+                    return Err(MoneyError::Overflow);
+                }
+                amount
+            }
+            None => amount.normalize(),
+        };
+        Ok(Money { amount, currency })
+    }
+
+    fn checked_add(&self, other: &Money) -> Result<Money> {
+        self.same_currency(other)?;
+        let amount = self.amount.checked_add(other.amount).ok_or(MoneyError::Overflow)?;
+        Ok(Money { amount, ..*self })
+    }
+
+    fn checked_sub(&self, other: &Money) -> Result<Money> {
+        self.same_currency(other)?;
+        let amount = self.amount.checked_sub(other.amount).ok_or(MoneyError::Overflow)?;
+        Ok(Money { amount, ..*self })
+    }
+
+    // Multiplying usually produces fractions of a cent,
+    // so the caller has to decide how to get rid of them
+    fn multiply(&self, factor: Decimal, rounding: RoundingStrategy) -> Result<Money> {
+        let amount = self.amount.checked_mul(factor).ok_or(MoneyError::Overflow)?;
+        Money::rounded(amount, self.currency, rounding)
+    }
+
+    fn same_currency(&self, other: &Money) -> Result<()> {
+        if self.currency == other.currency {
+            Ok(())
+        } else {
+            Err(MoneyError::CurrencyMismatch(self.currency.code, other.currency.code))
+        }
+    }
+
+    // Splits the amount by ratios, e.g. [1, 1, 1] for three equal parts.
+    // Rounding every part on its own loses or invents cents: 100 / 3 would
+    // give 3 * 33.33 = 99.99. Instead, the parts are rounded down and the
+    // leftover cents go to the parts that lost the most by rounding
+    fn allocate(&self, ratios: &[u32]) -> Result<Vec<Money>> {
+        let minor_units = self.currency.minor_units.ok_or_else(|| {
+            MoneyError::Allocation(format!("{} has no minor unit", self.currency.code))
+        })?;
+        let total_ratio: u64 = ratios.iter().map(|&ratio| u64::from(ratio)).sum();
+        if total_ratio == 0 {
+            return Err(MoneyError::Allocation("The ratios add up to zero".to_string()));
+        }
+
+        // Work with whole cents, the sign is added back at the end
+        let mut amount = self.amount;
+        amount.rescale(minor_units);
+        if amount.scale() != minor_units {
+            return Err(MoneyError::Overflow);
+        }
+        let total = amount.mantissa().unsigned_abs();
+        let total_ratio = u128::from(total_ratio);
+        let mut parts: Vec<u128> = Vec::with_capacity(ratios.len());
+        let mut remainders: Vec<(u128, usize)> = Vec::with_capacity(ratios.len());
+        for (index, &ratio) in ratios.iter().enumerate() {
+            let share = total.checked_mul(u128::from(ratio)).ok_or(MoneyError::Overflow)?;
+            parts.push(share / total_ratio);
+            remainders.push((share % total_ratio, index));
+        }
+        let mut leftover = total - parts.iter().sum::<u128>();
+        // Largest remainder first, ties go to the earlier part
+        remainders.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        for &(_, index) in &remainders {
+            if leftover == 0 {
+                break;
+            }
+            parts[index] += 1;
+            leftover -= 1;
+        }
+
+        parts
+            .into_iter()
+            .map(|part| {
+                let part = i128::try_from(part).map_err(|_| MoneyError::Overflow)?;
+                let part = if amount.is_sign_negative() { -part } else { part };
+                let amount = Decimal::try_from_i128_with_scale(part, minor_units)
+                    .map_err(|_| MoneyError::Overflow)?;
+                Ok(Money { amount, ..*self })
+            })
+            .collect()
+    }
+}
+
+// Always "1234.50 EUR", no matter where the program runs.
+// Thousands separators and symbols depend on the reader and belong into the UI
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.amount, self.currency.code)
+    }
+}
+
+// Accepts "1234.50 EUR" and "EUR 1234.50"
+impl str::FromStr for Money {
+    type Err = MoneyError;
+
+    fn from_str(s: &str) -> Result<Money> {
+        let mut parts = s.split_whitespace();
+        let (first, second) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(first), Some(second), None) => (first, second),
+            _ => {
+                return Err(MoneyError::Parse(format!(
+                    "Expected an amount and a currency code like '12.50 EUR', got '{}'",
+                    s
+                )))
+            }
+        };
+        let is_code = |part: &str| part.len() == 3 && part.chars().all(|c| c.is_ascii_uppercase());
+        let (amount, code) = if is_code(first) { (second, first) } else { (first, second) };
+        // Decimal would accept things like "1e3" or "1_000", which no one writes in a price
+        let digits = amount.strip_prefix(['-', '+']).unwrap_or(amount);
+        let (integer, fraction) = digits.split_once('.').unwrap_or((digits, "0"));
+        let is_digits = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit());
+        if !is_digits(integer) || !is_digits(fraction) {
+            return Err(MoneyError::Parse(format!(
+                "'{}' is not an amount, use a dot for decimals and no separators",
+                amount
+            )));
+        }
+        let amount: Decimal = amount
+            .parse()
+            .map_err(|e| MoneyError::Parse(format!("'{}' is not an amount: {}", amount, e)))?;
+        Money::new(amount, code)
+    }
+}
+
+// Exchange rates, only ever used together with a rounding strategy
+struct Rates {
+    rates: HashMap<(&'static str, &'static str), Decimal>,
+}
+
+impl Rates {
+    fn new() -> Self {
+        Rates {
+            rates: HashMap::new(),
+        }
+    }
+
+    // One `from` is worth `rate` of `to`
+    fn set(&mut self, from: &str, to: &str, rate: Decimal) -> Result<()> {
+        let from = currency(from)?.code;
+        let to = currency(to)?.code;
+        self.rates.insert((from, to), rate);
+        Ok(())
+    }
+
+    fn rate(&self, from: &'static str, to: &'static str) -> Result<Decimal> {
+        if from == to {
+            return Ok(Decimal::ONE);
+        }
+        if let Some(&rate) = self.rates.get(&(from, to)) {
+            return Ok(rate);
+        }
+        // The inverse works as well, as long as it isn't zero
+        self.rates
+            .get(&(to, from))
+            .and_then(|rate| Decimal::ONE.checked_div(*rate))
+            .ok_or(MoneyError::MissingRate(from, to))
+    }
+
+    fn convert(&self, money: &Money, to: &str, rounding: RoundingStrategy) -> Result<Money> {
+        let to = currency(to)?;
+        let rate = self.rate(money.currency.code, to.code)?;
+        let amount = money.amount.checked_mul(rate).ok_or(MoneyError::Overflow)?;
+        Money::rounded(amount, to, rounding)
+    }
+}
+
+fn main() {
+    println!("Known currencies: {}", CURRENCIES.len());
+    for code in &["CHF", "JPY", "KWD", "XAU"] {
+        let currency = currency(code).expect("Failed to find currency");
+        match currency.minor_units {
+            Some(minor_units) => println!(
+                "{} ({:03}) is the {} with {} decimal places",
+                currency.code, currency.number, currency.name, minor_units
+            ),
+            None => println!(
+                "{} ({:03}) is {} without a minor unit",
+                currency.code, currency.number, currency.name
+            ),
+        }
+    }
+
+    // With floats, 0.1 + 0.2 is 0.30000000000000004
+    let a = Money::new(Decimal::new(1, 1), "USD").expect("Failed to create money");
+    let b = Money::new(Decimal::new(2, 1), "USD").expect("Failed to create money");
+    println!("\n{} + {} = {}", a, b, a.checked_add(&b).expect("Failed to add"));
+    let euros = Money::from_minor(250, "EUR").expect("Failed to create money");
+    println!("{} - {} = {}", a, b, a.checked_sub(&b).expect("Failed to subtract"));
+    if let Err(e) = a.checked_add(&euros) {
+        println!("{} + {}: {}", a, euros, e);
+    }
+    if let Err(e) = Money::new(Decimal::new(1005, 3), "USD") {
+        println!("1.005 USD: {}", e);
+    }
+    println!("1.005 KWD is fine: {}", Money::new(Decimal::new(1005, 3), "KWD").expect("Failed to create money"));
+    // A Decimal holds 28 digits, which leaves no room for the cents of this one
+    if let Err(e) = "1000000000000000000000000000 USD".parse::<Money>() {
+        println!("10^27 USD: {}", e);
+    }
+
+    // A 5% tip leaves half a cent behind, the rounding decides what happens to it
+    let price: Money = "10.50 CHF".parse().expect("Failed to parse price");
+    let tip = Decimal::new(5, 2);
+    for rounding in &[
+        RoundingStrategy::MidpointNearestEven,
+        RoundingStrategy::MidpointAwayFromZero,
+        RoundingStrategy::ToZero,
+        RoundingStrategy::AwayFromZero,
+    ] {
+        let total = price.multiply(tip, *rounding).expect("Failed to multiply");
+        println!("{} * {} with {:?}: {}", price, tip, rounding, total);
+    }
+
+    // No cent gets lost
+    let bill: Money = "100 EUR".parse().expect("Failed to parse bill");
+    let parts = bill.allocate(&[1, 1, 1]).expect("Failed to allocate");
+    let parts: Vec<String> = parts.iter().map(|part| part.to_string()).collect();
+    println!("\n{} split three ways: {}", bill, parts.join(", "));
+    let yen = Money::from_minor(5, "JPY").expect("Failed to create money");
+    let parts: Vec<String> = yen
+        .allocate(&[1, 1, 1])
+        .expect("Failed to allocate")
+        .iter()
+        .map(|part| part.to_string())
+        .collect();
+    println!("{} split three ways: {}", yen, parts.join(", "));
+    let debt: Money = "-0.05 USD".parse().expect("Failed to parse debt");
+    let parts: Vec<String> = debt
+        .allocate(&[70, 30])
+        .expect("Failed to allocate")
+        .iter()
+        .map(|part| part.to_string())
+        .collect();
+    println!("{} split 70/30: {}", debt, parts.join(", "));
+
+    println!();
+    for input in &["CHF 12.5", "-3.141 KWD", "12,50 EUR", "1e3 USD", "12.50 ABC", "12.50"] {
+        match input.parse::<Money>() {
+            Ok(money) => println!("'{}' is {}", input, money),
+            Err(e) => println!("'{}': {}", input, e),
+        }
+    }
+
+    let mut rates = Rates::new();
+    rates.set("EUR", "USD", Decimal::new(10842, 4)).expect("Failed to set rate");
+    rates.set("USD", "JPY", Decimal::new(14953, 2)).expect("Failed to set rate");
+    let hundred: Money = "100.00 EUR".parse().expect("Failed to parse money");
+    let dollars = rates
+        .convert(&hundred, "USD", RoundingStrategy::MidpointNearestEven)
+        .expect("Failed to convert");
+    println!("\n{} is {}", hundred, dollars);
+    // Only the rate from EUR to USD is known, the inverse is calculated
+    let back = rates
+        .convert(&dollars, "EUR", RoundingStrategy::MidpointNearestEven)
+        .expect("Failed to convert");
+    println!("{} is {}", dollars, back);
+    let yen = rates
+        .convert(&dollars, "JPY", RoundingStrategy::ToZero)
+        .expect("Failed to convert");
+    println!("{} is {}", dollars, yen);
+    if let Err(e) = rates.convert(&hundred, "JPY", RoundingStrategy::ToZero) {
+        println!("{} to JPY: {}", hundred, e);
+    }
+}
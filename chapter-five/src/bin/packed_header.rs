@@ -0,0 +1,122 @@
+use chapter_five_derive::bitfield;
+
+// bitflags! is great for single bits, but the fields of a network
+// header are often several bits wide. These are the first 12 bytes
+// of an IPv4 header, split into the same 32 bit words as in RFC 791
+bitfield! {
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    struct VersionAndLength: u32 {
+        #[bits(4)]
+        version: u8,
+        // The header length, counted in 32 bit words
+        #[bits(4)]
+        header_length: u8,
+        #[bits(6)]
+        differentiated_services: u8,
+        #[bits(2)]
+        explicit_congestion: u8,
+        total_length: u16,
+    }
+}
+
+bitfield! {
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    struct Fragmentation: u32 {
+        identification: u16,
+        // Reserved bits are not accessible, and cleared when a header is read
+        #[bits(1)]
+        _reserved: bool,
+        dont_fragment: bool,
+        more_fragments: bool,
+        #[bits(13)]
+        fragment_offset: u16,
+    }
+}
+
+bitfield! {
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    struct RoutingAndChecksum: u32 {
+        time_to_live: u8,
+        // Enums only need to be convertible from and into u64
+        #[bits(8)]
+        protocol: Protocol,
+        checksum: u16,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Protocol {
+    Icmp = 1,
+    Tcp = 6,
+    Udp = 17,
+}
+
+impl From<Protocol> for u64 {
+    fn from(protocol: Protocol) -> u64 {
+        protocol as u64
+    }
+}
+
+impl TryFrom<u64> for Protocol {
+    // Unknown protocol numbers are returned as they are
+    type Error = u64;
+
+    fn try_from(number: u64) -> Result<Protocol, u64> {
+        match number {
+            1 => Ok(Protocol::Icmp),
+            6 => Ok(Protocol::Tcp),
+            17 => Ok(Protocol::Udp),
+            other => Err(other),
+        }
+    }
+}
+
+fn main() {
+    // The start of a real header, as it arrives over the network
+    let packet: [u8; 12] = [
+        0x45, 0x00, 0x00, 0x3c, 0x1c, 0x46, 0x40, 0x00, 0x40, 0x06, 0xb1, 0xe6,
+    ];
+    let first = VersionAndLength::from_be_bytes([packet[0], packet[1], packet[2], packet[3]]);
+    let second = Fragmentation::from_be_bytes([packet[4], packet[5], packet[6], packet[7]]);
+    let third = RoutingAndChecksum::from_be_bytes([packet[8], packet[9], packet[10], packet[11]]);
+    // Debug shows the decoded fields instead of a number
+    println!("{:#?}", first);
+    println!("{:#?}", second);
+    println!("{:#?}", third);
+    println!(
+        "The header is {} bytes long",
+        first.header_length() as usize * 4
+    );
+
+    // Build a header of our own
+    let mut header = VersionAndLength::new();
+    header.set_version(4).expect("4 fits into 4 bits");
+    header.set_header_length(5).expect("5 fits into 4 bits");
+    header.set_total_length(60).expect("Every u16 fits into 16 bits");
+    println!("\nOur header: {:08x}", header.bits());
+    assert_eq!(header.to_be_bytes(), [packet[0], packet[1], packet[2], packet[3]]);
+    // Little endian machines store the same header the other way around
+    println!("Big endian: {:02x?}", header.to_be_bytes());
+    println!("Little endian: {:02x?}", header.to_le_bytes());
+    assert_eq!(VersionAndLength::from_le_bytes(header.to_le_bytes()), header);
+
+    // Values that don't fit are rejected instead of silently corrupting other fields
+    if let Err(e) = header.set_header_length(20) {
+        println!("Failed to set the header length: {}", e);
+    }
+    println!("Header length is still {}", header.header_length());
+
+    let mut routing = RoutingAndChecksum::new();
+    routing.set_protocol(Protocol::Udp).expect("Protocols fit into 8 bits");
+    routing.set_time_to_live(64).expect("Every u8 fits into 8 bits");
+    println!("\n{:?}", routing);
+    // Bits from the network can contain anything
+    let unknown = RoutingAndChecksum::from_bits(0x4029_0000);
+    println!("{:?}", unknown);
+    println!("Protocol: {:?}", unknown.protocol());
+
+    let mut fragmentation = Fragmentation::from_bits(second.bits());
+    fragmentation.set_dont_fragment(false);
+    fragmentation.set_more_fragments(true);
+    println!("\n{:?}", fragmentation);
+}
@@ -0,0 +1,204 @@
+use chapter_five_derive::Validate;
+use std::collections::BTreeMap;
+use std::fmt;
+
+// Just like with HelloWorld, the trait has to be defined in the consumer crate
+trait Validate {
+    // Adds every problem it finds to errors, this is what the derive implements
+    fn validate_into(&self, errors: &mut ValidationErrors);
+
+    // Returns all problems at once, instead of stopping at the first one
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        self.validate_into(&mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+// A tree of problems, mirroring the structure of the validated value.
+// The problems with pets.1.name are found under
+// fields["pets"].fields["1"].fields["name"].messages
+#[derive(Debug, Default)]
+struct ValidationErrors {
+    messages: Vec<String>,
+    fields: BTreeMap<String, ValidationErrors>,
+}
+
+impl ValidationErrors {
+    fn add(&mut self, message: String) {
+        self.messages.push(message);
+    }
+
+    // Creates the entry on the first use, empty entries are ignored later
+    fn field(&mut self, name: &str) -> &mut ValidationErrors {
+        self.fields.entry(name.to_string()).or_default()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.messages.is_empty() && self.fields.values().all(ValidationErrors::is_empty)
+    }
+
+    // Every problem together with the path to its field, like "pets.1.name"
+    fn flatten(&self) -> Vec<(String, &str)> {
+        let mut flat: Vec<(String, &str)> = self
+            .messages
+            .iter()
+            .map(|message| (String::new(), message.as_str()))
+            .collect();
+        for (name, errors) in &self.fields {
+            for (path, message) in errors.flatten() {
+                let path = if path.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{}.{}", name, path)
+                };
+                flat.push((path, message));
+            }
+        }
+        flat
+    }
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (path, message) in self.flatten() {
+            if path.is_empty() {
+                writeln!(f, "{}", message)?;
+            } else {
+                writeln!(f, "{}: {}", path, message)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// The invariants of the burger from the builder recipe of chapter one,
+// declared instead of checked by hand in build()
+#[derive(Debug, Validate)]
+#[validate(custom = "no_vegetarian_bacon")]
+struct Burger {
+    #[validate(range(min = 1, max = 4))]
+    patty_count: i32,
+    vegetarian: bool,
+    bacon: bool,
+}
+
+fn no_vegetarian_bacon(burger: &Burger) -> Result<(), String> {
+    if burger.vegetarian && burger.bacon {
+        Err("Sorry, but we don't serve vegetarian bacon yet".to_string())
+    } else {
+        Ok(())
+    }
+}
+
+// A request to register a pet owner, like the ones from chapter four
+#[derive(Debug, Validate)]
+#[validate(custom = "not_too_many_pets_for_a_kid")]
+struct Registration {
+    #[validate(length(min = 1, max = 20))]
+    name: String,
+    // Ages are checked here, so there is no need for a NegativeAge error later
+    #[validate(range(min = 0, max = 150))]
+    age: i32,
+    #[validate(email)]
+    email: String,
+    // Optional fields are only checked when they are present
+    #[validate(regex = r"^\+?[0-9 ]{6,20}$")]
+    phone: Option<String>,
+    #[validate(length(max = 5), nested)]
+    pets: Vec<Pet>,
+    #[validate(nested)]
+    address: Address,
+}
+
+#[derive(Debug, Validate)]
+struct Pet {
+    #[validate(length(min = 1), custom = "capitalized")]
+    name: String,
+    #[validate(range(max = 50))]
+    age: Option<u8>,
+}
+
+#[derive(Debug, Validate)]
+struct Address {
+    #[validate(length(min = 1))]
+    street: String,
+    #[validate(regex = r"^[0-9]{4,5}$")]
+    zip_code: String,
+}
+
+fn capitalized(name: &str) -> Result<(), String> {
+    match name.chars().next() {
+        Some(first) if first.is_lowercase() => Err(format!("'{}' should start with a capital letter", name)),
+        _ => Ok(()),
+    }
+}
+
+// Checks can look at more than one field at once
+fn not_too_many_pets_for_a_kid(registration: &Registration) -> Result<(), String> {
+    if registration.age < 12 && registration.pets.len() > 1 {
+        Err("Kids below 12 can register only one pet".to_string())
+    } else {
+        Ok(())
+    }
+}
+
+fn main() {
+    let burger = Burger {
+        patty_count: 5,
+        vegetarian: true,
+        bacon: true,
+    };
+    match burger.validate() {
+        Ok(()) => println!("The burger is fine"),
+        Err(errors) => print!("The burger is invalid:\n{}", errors),
+    }
+
+    let valid = Registration {
+        name: "Molly".to_string(),
+        age: 27,
+        email: "molly@example.com".to_string(),
+        phone: None,
+        pets: vec![Pet {
+            name: "Waldo".to_string(),
+            age: Some(3),
+        }],
+        address: Address {
+            street: "Baker Street 221b".to_string(),
+            zip_code: "8000".to_string(),
+        },
+    };
+    if valid.validate().is_ok() {
+        println!("\n{} is registered", valid.name);
+    }
+
+    let invalid = Registration {
+        name: String::new(),
+        age: 8,
+        email: "not an email".to_string(),
+        phone: Some("call me maybe".to_string()),
+        pets: vec![
+            Pet {
+                name: "Speedy".to_string(),
+                age: Some(64),
+            },
+            Pet {
+                name: "meows".to_string(),
+                age: None,
+            },
+        ],
+        address: Address {
+            street: "Baker Street 221b".to_string(),
+            zip_code: "NW1".to_string(),
+        },
+    };
+    if let Err(errors) = invalid.validate() {
+        print!("\nThe registration is invalid:\n{}", errors);
+        // The tree can also be used to look up the problems of a single field
+        print!("\nThe second pet alone:\n{}", errors.fields["pets"].fields["1"]);
+    }
+}
@@ -0,0 +1,223 @@
+use serde::de::DeserializeOwned;
+use serde_derive::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use std::{env, error, fmt};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct PetOwner {
+    name: String,
+    age: u8,
+    pets: Vec<Pet>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Pet {
+    name: String,
+    species: AllowedSpecies,
+    age: Option<u8>,
+    colour: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum AllowedSpecies {
+    Dog,
+    Turtle,
+    Cat,
+}
+
+fn main() {
+    // pet_owner.json is the file written by json.rs
+    let pet_owner: PetOwner = load("pet_owner.json").expect("Failed to load pet_owner.json");
+
+    // The same call works for every format, only the extension changes
+    let directory = env::temp_dir();
+    for name in &["pet_owner.json", "pet_owner.msgpack", "pet_owner.cbor"] {
+        let path = directory.join(name);
+        save(&path, &pet_owner).expect("Failed to save pet owner");
+        let loaded: PetOwner = load(&path).expect("Failed to load pet owner");
+        assert_eq!(loaded, pet_owner, "{} didn't survive the round trip", name);
+        let size = path.metadata().expect("Failed to read metadata").len();
+        println!("Saved and loaded {} ({} bytes)", path.display(), size);
+    }
+    match save(directory.join("pet_owner.xml"), &pet_owner) {
+        Ok(_) => println!("Saving as XML unexpectedly worked"),
+        Err(e) => println!("Saving as XML fails: {}", e),
+    }
+
+    // Only meaningful with cargo run --release
+    println!("\nEncoding and decoding the pet owner {} times:", ROUNDS);
+    println!("{:<22} {:>6} {:>12} {:>12}", "format", "bytes", "encode", "decode");
+    for format in &[Format::Json, Format::MessagePack, Format::MessagePackCompact, Format::Cbor] {
+        let (size, encode, decode) = benchmark(*format, &pet_owner).expect("Failed to run benchmark");
+        println!("{:<22} {:>6} {:>12?} {:>12?}", format, size, encode, decode);
+    }
+}
+
+const ROUNDS: u32 = 10_000;
+
+fn benchmark(format: Format, pet_owner: &PetOwner) -> Result<(usize, Duration, Duration), FormatError> {
+    let mut bytes = Vec::new();
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        bytes.clear();
+        format.serialize(&mut bytes, pet_owner)?;
+    }
+    let encode = start.elapsed() / ROUNDS;
+
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        let decoded: PetOwner = format.deserialize(&bytes[..])?;
+        assert_eq!(&decoded, pet_owner);
+    }
+    let decode = start.elapsed() / ROUNDS;
+    Ok((bytes.len(), encode, decode))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Json,
+    // Structs are written as maps, keyed by field name.
+    // Slightly bigger, but old files keep working when fields are added or reordered
+    MessagePack,
+    // Structs are written as arrays, so they only work with the exact same struct
+    MessagePackCompact,
+    Cbor,
+}
+
+impl Format {
+    fn from_path(path: &Path) -> Result<Format, FormatError> {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => Ok(Format::Json),
+            Some("msgpack") | Some("mp") => Ok(Format::MessagePack),
+            Some("cbor") => Ok(Format::Cbor),
+            _ => Err(FormatError::UnknownFormat(path.display().to_string())),
+        }
+    }
+
+    fn serialize<W: Write, T: serde::Serialize>(self, writer: &mut W, value: &T) -> Result<(), FormatError> {
+        match self {
+            Format::Json => serde_json::to_writer(writer, value)?,
+            Format::MessagePack => rmp_serde::encode::write_named(writer, value)?,
+            Format::MessagePackCompact => rmp_serde::encode::write(writer, value)?,
+            Format::Cbor => ciborium::ser::into_writer(value, writer)?,
+        }
+        Ok(())
+    }
+
+    fn deserialize<R: Read, T: DeserializeOwned>(self, reader: R) -> Result<T, FormatError> {
+        Ok(match self {
+            Format::Json => serde_json::from_reader(reader)?,
+            // The decoder understands both ways of writing structs
+            Format::MessagePack | Format::MessagePackCompact => rmp_serde::from_read(reader)?,
+            Format::Cbor => ciborium::de::from_reader(reader)?,
+        })
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Format::Json => "JSON",
+            Format::MessagePack => "MessagePack",
+            Format::MessagePackCompact => "MessagePack (compact)",
+            Format::Cbor => "CBOR",
+        };
+        // Pad, so the format can be used in tables
+        f.pad(name)
+    }
+}
+
+// Works for any serde type, the format is picked by the file extension
+fn save<P: AsRef<Path>, T: serde::Serialize>(path: P, value: &T) -> Result<(), FormatError> {
+    let format = Format::from_path(path.as_ref())?;
+    let mut writer = BufWriter::new(File::create(path)?);
+    format.serialize(&mut writer, value)?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn load<P: AsRef<Path>, T: DeserializeOwned>(path: P) -> Result<T, FormatError> {
+    let format = Format::from_path(path.as_ref())?;
+    format.deserialize(BufReader::new(File::open(path)?))
+}
+
+// Our custom error, following the pattern from chapter six
+#[derive(Debug)]
+enum FormatError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    MessagePackEncode(rmp_serde::encode::Error),
+    MessagePackDecode(rmp_serde::decode::Error),
+    CborEncode(ciborium::ser::Error<io::Error>),
+    CborDecode(ciborium::de::Error<io::Error>),
+    UnknownFormat(String),
+}
+
+impl error::Error for FormatError {
+    fn cause(&self) -> Option<&dyn error::Error> {
+        match *self {
+            FormatError::Io(ref err) => Some(err),
+            FormatError::Json(ref err) => Some(err),
+            FormatError::MessagePackEncode(ref err) => Some(err),
+            FormatError::MessagePackDecode(ref err) => Some(err),
+            FormatError::CborEncode(ref err) => Some(err),
+            FormatError::CborDecode(ref err) => Some(err),
+            FormatError::UnknownFormat(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FormatError::Io(ref err) => write!(f, "IO error: {}", err),
+            FormatError::Json(ref err) => write!(f, "JSON error: {}", err),
+            FormatError::MessagePackEncode(ref err) => write!(f, "MessagePack encode error: {}", err),
+            FormatError::MessagePackDecode(ref err) => write!(f, "MessagePack decode error: {}", err),
+            FormatError::CborEncode(ref err) => write!(f, "CBOR encode error: {}", err),
+            FormatError::CborDecode(ref err) => write!(f, "CBOR decode error: {}", err),
+            FormatError::UnknownFormat(ref path) => {
+                write!(f, "Can't tell the format of {}, use .json, .msgpack or .cbor", path)
+            }
+        }
+    }
+}
+
+impl From<io::Error> for FormatError {
+    fn from(err: io::Error) -> FormatError {
+        FormatError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for FormatError {
+    fn from(err: serde_json::Error) -> FormatError {
+        FormatError::Json(err)
+    }
+}
+
+impl From<rmp_serde::encode::Error> for FormatError {
+    fn from(err: rmp_serde::encode::Error) -> FormatError {
+        FormatError::MessagePackEncode(err)
+    }
+}
+
+impl From<rmp_serde::decode::Error> for FormatError {
+    fn from(err: rmp_serde::decode::Error) -> FormatError {
+        FormatError::MessagePackDecode(err)
+    }
+}
+
+impl From<ciborium::ser::Error<io::Error>> for FormatError {
+    fn from(err: ciborium::ser::Error<io::Error>) -> FormatError {
+        FormatError::CborEncode(err)
+    }
+}
+
+impl From<ciborium::de::Error<io::Error>> for FormatError {
+    fn from(err: ciborium::de::Error<io::Error>) -> FormatError {
+        FormatError::CborDecode(err)
+    }
+}
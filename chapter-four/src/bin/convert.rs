@@ -0,0 +1,462 @@
+use serde_json::{Map, Number, Value};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::{env, error, fmt, process, result};
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(e) = run(&args) {
+        eprintln!("Error: {}", e);
+        if let ConvertError::Usage(_) = e {
+            eprintln!(
+                "\nUsage: convert <input> <output> [--from <format>] [--to <format>]\n\
+                 \n\
+                 Formats: csv, json, ndjson, toml. They are guessed from the file\n\
+                 extensions, use '-' together with --from / --to for stdin / stdout.\n\
+                 Nested objects become dotted CSV columns like 'person.name' and back."
+            );
+            process::exit(2);
+        }
+        process::exit(1);
+    }
+}
+
+fn run(args: &[String]) -> Result<()> {
+    let mut paths = Vec::new();
+    let mut from = None;
+    let mut to = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--from" => from = Some(Format::from_name(next_value(&mut args, arg)?)?),
+            "--to" => to = Some(Format::from_name(next_value(&mut args, arg)?)?),
+            _ => paths.push(arg.as_str()),
+        }
+    }
+    let (input, output) = match paths.as_slice() {
+        [input, output] => (*input, *output),
+        _ => return Err(ConvertError::Usage("Expected an input and an output".to_string())),
+    };
+    let from = from.map_or_else(|| Format::from_path(input), Ok)?;
+    let to = to.map_or_else(|| Format::from_path(output), Ok)?;
+
+    let reader: Box<dyn Read> = if input == "-" {
+        Box::new(io::stdin())
+    } else {
+        Box::new(File::open(input)?)
+    };
+    let value = read(BufReader::new(reader), from)?;
+
+    let writer: Box<dyn Write> = if output == "-" {
+        Box::new(io::stdout())
+    } else {
+        Box::new(File::create(output)?)
+    };
+    let mut writer = BufWriter::new(writer);
+    write(&mut writer, &value, to)?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn next_value<'a, I: Iterator<Item = &'a String>>(args: &mut I, flag: &str) -> Result<&'a str> {
+    args.next()
+        .map(String::as_str)
+        .ok_or_else(|| ConvertError::Usage(format!("Missing value for {}", flag)))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Csv,
+    Json,
+    Ndjson,
+    Toml,
+}
+
+impl Format {
+    fn from_name(name: &str) -> Result<Format> {
+        match name {
+            "csv" => Ok(Format::Csv),
+            "json" => Ok(Format::Json),
+            "ndjson" | "jsonl" => Ok(Format::Ndjson),
+            "toml" => Ok(Format::Toml),
+            _ => Err(ConvertError::Usage(format!("Unknown format '{}'", name))),
+        }
+    }
+
+    fn from_path(path: &str) -> Result<Format> {
+        match Path::new(path).extension().and_then(|extension| extension.to_str()) {
+            Some(extension) => Format::from_name(extension),
+            None => Err(ConvertError::Usage(format!(
+                "Can't tell the format of '{}', please use --from or --to",
+                path
+            ))),
+        }
+    }
+}
+
+// Our custom error, following the pattern from chapter six
+#[derive(Debug)]
+enum ConvertError {
+    Io(io::Error),
+    Csv(csv::Error),
+    Json(serde_json::Error),
+    TomlDe(toml::de::Error),
+    TomlSer(toml::ser::Error),
+    // The data can't be expressed in the target format
+    Unrepresentable { path: String, reason: String },
+    Usage(String),
+}
+
+type Result<T> = result::Result<T, ConvertError>;
+
+impl error::Error for ConvertError {
+    fn cause(&self) -> Option<&dyn error::Error> {
+        match *self {
+            ConvertError::Io(ref err) => Some(err),
+            ConvertError::Csv(ref err) => Some(err),
+            ConvertError::Json(ref err) => Some(err),
+            ConvertError::TomlDe(ref err) => Some(err),
+            ConvertError::TomlSer(ref err) => Some(err),
+            ConvertError::Unrepresentable { .. } | ConvertError::Usage(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConvertError::Io(ref err) => write!(f, "IO error: {}", err),
+            ConvertError::Csv(ref err) => write!(f, "CSV error: {}", err),
+            ConvertError::Json(ref err) => write!(f, "JSON error: {}", err),
+            ConvertError::TomlDe(ref err) => write!(f, "TOML parse error: {}", err),
+            ConvertError::TomlSer(ref err) => write!(f, "TOML write error: {}", err),
+            ConvertError::Unrepresentable { ref path, ref reason } => {
+                let path = if path.is_empty() { "the document root" } else { path };
+                write!(f, "Can't convert {}: {}", path, reason)
+            }
+            ConvertError::Usage(ref message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<io::Error> for ConvertError {
+    fn from(err: io::Error) -> ConvertError {
+        ConvertError::Io(err)
+    }
+}
+
+impl From<csv::Error> for ConvertError {
+    fn from(err: csv::Error) -> ConvertError {
+        ConvertError::Csv(err)
+    }
+}
+
+impl From<serde_json::Error> for ConvertError {
+    fn from(err: serde_json::Error) -> ConvertError {
+        ConvertError::Json(err)
+    }
+}
+
+impl From<toml::de::Error> for ConvertError {
+    fn from(err: toml::de::Error) -> ConvertError {
+        ConvertError::TomlDe(err)
+    }
+}
+
+impl From<toml::ser::Error> for ConvertError {
+    fn from(err: toml::ser::Error) -> ConvertError {
+        ConvertError::TomlSer(err)
+    }
+}
+
+fn unrepresentable(path: &str, reason: &str) -> ConvertError {
+    ConvertError::Unrepresentable {
+        path: path.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+fn read<R: BufRead>(mut reader: R, format: Format) -> Result<Value> {
+    match format {
+        Format::Json => Ok(serde_json::from_reader(reader)?),
+        // Every line is a document of its own
+        Format::Ndjson => {
+            let mut records = Vec::new();
+            for line in reader.lines() {
+                let line = line?;
+                if !line.trim().is_empty() {
+                    records.push(serde_json::from_str(&line)?);
+                }
+            }
+            Ok(Value::Array(records))
+        }
+        Format::Toml => {
+            let mut content = String::new();
+            reader.read_to_string(&mut content)?;
+            let value: toml::Value = toml::from_str(&content)?;
+            toml_to_json(value, "")
+        }
+        Format::Csv => {
+            let mut rdr = csv::Reader::from_reader(reader);
+            let headers = rdr.headers()?.clone();
+            let mut records = Vec::new();
+            for result in rdr.records() {
+                let record = result?;
+                let mut row = Map::new();
+                for (header, cell) in headers.iter().zip(record.iter()) {
+                    row.insert(header.to_string(), infer_type(cell));
+                }
+                let path = format!("/{}", records.len());
+                records.push(unflatten(row, &path)?);
+            }
+            Ok(Value::Array(records))
+        }
+    }
+}
+
+fn write<W: Write>(writer: &mut W, value: &Value, format: Format) -> Result<()> {
+    match format {
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut *writer, value)?;
+            writeln!(writer)?;
+        }
+        Format::Ndjson => {
+            let records = match value {
+                Value::Array(records) => records.iter().collect(),
+                other => vec![other],
+            };
+            for record in records {
+                serde_json::to_writer(&mut *writer, record)?;
+                writeln!(writer)?;
+            }
+        }
+        Format::Toml => {
+            let value = json_to_toml(value, "")?;
+            if !value.is_table() {
+                return Err(unrepresentable("", "a TOML document has to be a table, not a list or a single value"));
+            }
+            writer.write_all(toml::to_string_pretty(&value)?.as_bytes())?;
+        }
+        Format::Csv => {
+            let records = match value {
+                Value::Array(records) => records.iter().collect(),
+                object @ Value::Object(_) => vec![object],
+                _ => return Err(unrepresentable("", "CSV needs a list of objects")),
+            };
+            let mut rows = Vec::new();
+            for (index, record) in records.into_iter().enumerate() {
+                let mut row = Vec::new();
+                let path = format!("/{}", index);
+                match record {
+                    Value::Object(object) => flatten(object, "", &path, &mut row)?,
+                    _ => return Err(unrepresentable(&path, "CSV rows have to be objects")),
+                }
+                rows.push(row);
+            }
+            // The header is the union of all columns, in the order we first saw them
+            let mut headers: Vec<String> = Vec::new();
+            for row in &rows {
+                for (column, _) in row {
+                    if !headers.contains(column) {
+                        headers.push(column.clone());
+                    }
+                }
+            }
+            // {"a": 1} and {"a": {"b": 2}} would need both "a" and "a.b",
+            // which can't be turned back into a single value
+            for header in &headers {
+                let nested = format!("{}.", header);
+                if let Some(other) = headers.iter().find(|other| other.starts_with(&nested)) {
+                    return Err(unrepresentable(
+                        &format!("/{}", header.replace('.', "/")),
+                        &format!("the column {} clashes with {}, it is a value in some rows and nested in others", header, other),
+                    ));
+                }
+            }
+            let mut wtr = csv::Writer::from_writer(writer);
+            wtr.write_record(&headers)?;
+            for row in rows {
+                let cells = headers.iter().map(|header| {
+                    row.iter()
+                        .find(|(column, _)| column == header)
+                        .map(|(_, cell)| cell.clone())
+                        .unwrap_or_default()
+                });
+                wtr.write_record(cells)?;
+            }
+            wtr.flush()?;
+        }
+    }
+    Ok(())
+}
+
+// CSV only knows text, so we guess what a cell was meant to be
+fn infer_type(cell: &str) -> Value {
+    if cell.is_empty() {
+        return Value::Null;
+    }
+    if cell == "true" || cell == "false" {
+        return Value::Bool(cell == "true");
+    }
+    // Leading zeros usually mean an identifier like a zip code, not a number
+    let looks_like_id = cell.len() > 1 && cell.starts_with('0') && !cell.starts_with("0.");
+    if !looks_like_id {
+        if let Ok(integer) = cell.parse::<i64>() {
+            return Value::from(integer);
+        }
+        if let Some(number) = cell.parse::<f64>().ok().and_then(Number::from_f64) {
+            // Only plain decimal notation, so "inf" or "1e5" stay text
+            if cell.chars().all(|c| c.is_ascii_digit() || c == '.' || c == '-') {
+                return Value::Number(number);
+            }
+        }
+    }
+    Value::String(cell.to_string())
+}
+
+// Turn {"person": {"name": "Jan"}} into the column "person.name".
+// Array elements get their index as part of the name, e.g. "tags.0"
+fn flatten(object: &Map<String, Value>, prefix: &str, path: &str, row: &mut Vec<(String, String)>) -> Result<()> {
+    for (key, value) in object {
+        let column = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        let path = format!("{}/{}", path, key);
+        match value {
+            // Without any columns, they would silently disappear
+            Value::Object(child) if child.is_empty() => {
+                return Err(unrepresentable(&path, "CSV has no way to write an empty object"))
+            }
+            Value::Array(items) if items.is_empty() => {
+                return Err(unrepresentable(&path, "CSV has no way to write an empty list"))
+            }
+            Value::Object(child) => flatten(child, &column, &path, row)?,
+            Value::Array(items) => {
+                let as_object: Map<String, Value> = items
+                    .iter()
+                    .enumerate()
+                    .map(|(index, item)| (index.to_string(), item.clone()))
+                    .collect();
+                flatten(&as_object, &column, &path, row)?
+            }
+            Value::Null => row.push((column, String::new())),
+            Value::String(string) => row.push((column, string.clone())),
+            other => row.push((column, other.to_string())),
+        }
+    }
+    Ok(())
+}
+
+// The reverse of flatten: dotted columns become nested objects again
+fn unflatten(row: Map<String, Value>, path: &str) -> Result<Value> {
+    let clash = |column: &str| {
+        unrepresentable(
+            &format!("{}/{}", path, column.replace('.', "/")),
+            "a column can't be a value and hold nested columns like \"a\" and \"a.b\" at the same time",
+        )
+    };
+    let mut root = Map::new();
+    for (column, value) in row {
+        let mut parts: Vec<&str> = column.split('.').collect();
+        let last = parts.pop().expect("split always yields at least one part");
+        let mut current = &mut root;
+        for part in parts {
+            current = current
+                .entry(part.to_string())
+                .or_insert_with(|| Value::Object(Map::new()))
+                .as_object_mut()
+                .ok_or_else(|| clash(&column))?;
+        }
+        if current.insert(last.to_string(), value).is_some() {
+            return Err(clash(&column));
+        }
+    }
+    Ok(restore_arrays(Value::Object(root)))
+}
+
+// Objects with the keys "0", "1", "2"... were arrays before flattening
+fn restore_arrays(value: Value) -> Value {
+    match value {
+        Value::Object(object) => {
+            let is_array = !object.is_empty()
+                && (0..object.len()).all(|index| object.contains_key(&index.to_string()));
+            if is_array {
+                let mut object = object;
+                Value::Array(
+                    (0..object.len())
+                        .map(|index| restore_arrays(object.remove(&index.to_string()).expect("Checked above")))
+                        .collect(),
+                )
+            } else {
+                Value::Object(
+                    object
+                        .into_iter()
+                        .map(|(key, value)| (key, restore_arrays(value)))
+                        .collect(),
+                )
+            }
+        }
+        other => other,
+    }
+}
+
+fn toml_to_json(value: toml::Value, path: &str) -> Result<Value> {
+    Ok(match value {
+        toml::Value::String(string) => Value::String(string),
+        toml::Value::Integer(integer) => Value::from(integer),
+        toml::Value::Float(float) => match Number::from_f64(float) {
+            Some(number) => Value::Number(number),
+            None => return Err(unrepresentable(path, "JSON has no NaN or infinity")),
+        },
+        toml::Value::Boolean(boolean) => Value::Bool(boolean),
+        // JSON has no date type, RFC 3339 strings are the usual stand-in
+        toml::Value::Datetime(datetime) => Value::String(datetime.to_string()),
+        toml::Value::Array(array) => Value::Array(
+            array
+                .into_iter()
+                .enumerate()
+                .map(|(index, item)| toml_to_json(item, &format!("{}/{}", path, index)))
+                .collect::<Result<_>>()?,
+        ),
+        toml::Value::Table(table) => Value::Object(
+            table
+                .into_iter()
+                .map(|(key, value)| {
+                    let path = format!("{}/{}", path, key);
+                    Ok((key, toml_to_json(value, &path)?))
+                })
+                .collect::<Result<_>>()?,
+        ),
+    })
+}
+
+fn json_to_toml(value: &Value, path: &str) -> Result<toml::Value> {
+    Ok(match value {
+        Value::Null => return Err(unrepresentable(path, "TOML has no null value")),
+        Value::Bool(boolean) => toml::Value::Boolean(*boolean),
+        Value::Number(number) => match number.as_i64() {
+            Some(integer) => toml::Value::Integer(integer),
+            None if number.is_u64() => {
+                return Err(unrepresentable(path, "TOML integers are limited to 64 bit signed values"))
+            }
+            None => toml::Value::Float(number.as_f64().expect("Every JSON number fits into an f64")),
+        },
+        Value::String(string) => toml::Value::String(string.clone()),
+        Value::Array(array) => toml::Value::Array(
+            array
+                .iter()
+                .enumerate()
+                .map(|(index, item)| json_to_toml(item, &format!("{}/{}", path, index)))
+                .collect::<Result<_>>()?,
+        ),
+        Value::Object(object) => toml::Value::Table(
+            object
+                .iter()
+                .map(|(key, value)| Ok((key.clone(), json_to_toml(value, &format!("{}/{}", path, key))?)))
+                .collect::<Result<_>>()?,
+        ),
+    })
+}
@@ -0,0 +1,430 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashSet};
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::{BufReader, Read};
+use std::path::Path;
+use std::{env, fmt, process};
+
+fn main() {
+    let options = match Options::parse(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n", e);
+            eprintln!(
+                "Usage: csv_profile [file] [--name <StructName>] [--samples <count>]\n\
+                 \n\
+                 Profiles solar_system_compared_to_earth.csv, the file written by csv.rs, by default.\n\
+                 Prints what every column contains, followed by a struct to deserialize it into."
+            );
+            process::exit(2);
+        }
+    };
+
+    let file = match File::open(&options.path) {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("Failed to open {}: {}", options.path, e);
+            process::exit(1);
+        }
+    };
+    let profiles = match profile(BufReader::new(file), options.samples) {
+        Ok(profiles) => profiles,
+        Err(e) => {
+            eprintln!("Failed to profile {}: {}", options.path, e);
+            process::exit(1);
+        }
+    };
+
+    for profile in &profiles {
+        println!("{}", profile);
+    }
+    let name = options.name.unwrap_or_else(|| {
+        let stem = Path::new(&options.path).file_stem().and_then(|stem| stem.to_str());
+        to_camel_case(stem.unwrap_or("Record"))
+    });
+    println!("{}", rust_struct(&name, &profiles));
+}
+
+struct Options {
+    path: String,
+    name: Option<String>,
+    samples: usize,
+}
+
+impl Options {
+    fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+        let mut options = Options {
+            path: "solar_system_compared_to_earth.csv".to_string(),
+            name: None,
+            samples: 3,
+        };
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--name" => options.name = Some(args.next().ok_or("Missing value for --name")?),
+                "--samples" => {
+                    let value = args.next().ok_or("Missing value for --samples")?;
+                    options.samples = value.parse().map_err(|_| format!("Invalid sample count '{}'", value))?;
+                }
+                flag if flag.starts_with('-') => return Err(format!("Unknown option '{}'", flag)),
+                path => options.path = path.to_string(),
+            }
+        }
+        Ok(options)
+    }
+}
+
+// The most specific type every value of a column fits into
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ColumnType {
+    // We haven't seen a single value yet
+    Unknown,
+    Bool,
+    Integer,
+    Float,
+    Date,
+    String,
+}
+
+impl ColumnType {
+    fn of(cell: &str) -> ColumnType {
+        if cell == "true" || cell == "false" {
+            ColumnType::Bool
+        } else if has_leading_zero(cell) {
+            // Leading zeros usually mean an identifier like a zip code, not a number
+            ColumnType::String
+        } else if cell.parse::<i64>().is_ok() {
+            ColumnType::Integer
+        } else if cell.parse::<f64>().is_ok_and(f64::is_finite) {
+            // This also accepts "inf" and "NaN", which is_finite keeps out, and 1e5, which stays a float
+            ColumnType::Float
+        } else if is_date(cell) {
+            ColumnType::Date
+        } else {
+            ColumnType::String
+        }
+    }
+
+    // What a column is when it contains values of both types
+    fn merge(self, other: ColumnType) -> ColumnType {
+        match (self, other) {
+            (ColumnType::Unknown, other) | (other, ColumnType::Unknown) => other,
+            (a, b) if a == b => a,
+            // Every integer is a valid float, too
+            (ColumnType::Integer, ColumnType::Float) | (ColumnType::Float, ColumnType::Integer) => ColumnType::Float,
+            _ => ColumnType::String,
+        }
+    }
+
+    fn is_numeric(self) -> bool {
+        self == ColumnType::Integer || self == ColumnType::Float
+    }
+
+    fn rust_type(self) -> &'static str {
+        match self {
+            ColumnType::Bool => "bool",
+            ColumnType::Integer => "i64",
+            ColumnType::Float => "f64",
+            // A real date type would need chrono, a string always works
+            ColumnType::Date | ColumnType::String | ColumnType::Unknown => "String",
+        }
+    }
+}
+
+impl fmt::Display for ColumnType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ColumnType::Unknown => "unknown",
+            ColumnType::Bool => "bool",
+            ColumnType::Integer => "integer",
+            ColumnType::Float => "float",
+            ColumnType::Date => "date",
+            ColumnType::String => "string",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+fn has_leading_zero(cell: &str) -> bool {
+    let digits = cell.strip_prefix('-').unwrap_or(cell);
+    digits.len() > 1 && digits.starts_with('0') && !digits.starts_with("0.")
+}
+
+// Dates in ISO 8601 form, like 2021-12-24
+fn is_date(cell: &str) -> bool {
+    let parts: Vec<&str> = cell.split('-').collect();
+    let (year, month, day) = match parts.as_slice() {
+        [year, month, day] if year.len() == 4 && month.len() == 2 && day.len() == 2 => (year, month, day),
+        _ => return false,
+    };
+    let (year, month, day) = match (year.parse::<u32>(), month.parse::<u32>(), day.parse::<u32>()) {
+        (Ok(year), Ok(month), Ok(day)) => (year, month, day),
+        _ => return false,
+    };
+    let is_leap_year = year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400));
+    let days_in_month = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if is_leap_year => 29,
+        2 => 28,
+        _ => return false,
+    };
+    (1..=days_in_month).contains(&day)
+}
+
+// Estimates the number of distinct values without remembering all of them.
+// We hash every value and only keep the K smallest hashes. If the hashes
+// are spread evenly, the K-th smallest one tells us how densely they are
+// packed, and with that how many different ones there must be in total.
+struct DistinctCounter {
+    smallest: BTreeSet<u64>,
+}
+
+impl DistinctCounter {
+    const K: usize = 1024;
+
+    fn new() -> DistinctCounter {
+        DistinctCounter {
+            smallest: BTreeSet::new(),
+        }
+    }
+
+    fn add(&mut self, value: &str) {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        let hash = hasher.finish();
+        if self.smallest.len() < Self::K {
+            self.smallest.insert(hash);
+        } else if hash < *self.smallest.last().expect("The set is full") && self.smallest.insert(hash) {
+            self.smallest.pop_last();
+        }
+    }
+
+    // Exact as long as there are fewer than K distinct values
+    fn estimate(&self) -> (u64, bool) {
+        if self.smallest.len() < Self::K {
+            return (self.smallest.len() as u64, true);
+        }
+        let largest = *self.smallest.last().expect("The set is full") as f64;
+        let fraction = largest / u64::MAX as f64;
+        (((Self::K - 1) as f64 / fraction) as u64, false)
+    }
+}
+
+// Everything we learned about a single column
+struct ColumnProfile {
+    name: String,
+    column_type: ColumnType,
+    count: u64,
+    nulls: u64,
+    // Running mean and variance, using Welford's algorithm so we
+    // never need to keep the values around
+    mean: f64,
+    squared_deviations: f64,
+    // Numbers compare by value, everything else alphabetically. Whether
+    // a column is numeric can change with every value, so we keep both
+    // and only decide which one to show at the end
+    numeric_min: Option<(f64, String)>,
+    numeric_max: Option<(f64, String)>,
+    min: Option<String>,
+    max: Option<String>,
+    distinct: DistinctCounter,
+    samples: Vec<String>,
+    max_samples: usize,
+}
+
+impl ColumnProfile {
+    fn new(name: &str, max_samples: usize) -> ColumnProfile {
+        ColumnProfile {
+            name: name.to_string(),
+            column_type: ColumnType::Unknown,
+            count: 0,
+            nulls: 0,
+            mean: 0.0,
+            squared_deviations: 0.0,
+            numeric_min: None,
+            numeric_max: None,
+            min: None,
+            max: None,
+            distinct: DistinctCounter::new(),
+            samples: Vec::new(),
+            max_samples,
+        }
+    }
+
+    fn add(&mut self, cell: &str) {
+        self.count += 1;
+        if cell.is_empty() {
+            self.nulls += 1;
+            return;
+        }
+        self.column_type = self.column_type.merge(ColumnType::of(cell));
+        self.distinct.add(cell);
+        if self.samples.len() < self.max_samples && !self.samples.iter().any(|sample| sample == cell) {
+            self.samples.push(cell.to_string());
+        }
+
+        if self.min.as_deref().is_none_or(|min| cell < min) {
+            self.min = Some(cell.to_string());
+        }
+        if self.max.as_deref().is_none_or(|max| max < cell) {
+            self.max = Some(cell.to_string());
+        }
+
+        if let Ok(number) = cell.parse::<f64>() {
+            if self.numeric_min.as_ref().is_none_or(|(min, _)| number < *min) {
+                self.numeric_min = Some((number, cell.to_string()));
+            }
+            if self.numeric_max.as_ref().is_none_or(|(max, _)| *max < number) {
+                self.numeric_max = Some((number, cell.to_string()));
+            }
+            let seen = (self.count - self.nulls) as f64;
+            let delta = number - self.mean;
+            self.mean += delta / seen;
+            self.squared_deviations += delta * (number - self.mean);
+        }
+    }
+
+    fn is_nullable(&self) -> bool {
+        self.nulls > 0
+    }
+
+    fn stddev(&self) -> f64 {
+        let values = self.count - self.nulls;
+        if values < 2 {
+            return 0.0;
+        }
+        (self.squared_deviations / (values - 1) as f64).sqrt()
+    }
+}
+
+impl fmt::Display for ColumnProfile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", self.name)?;
+        let nullable = if self.is_nullable() { ", nullable" } else { "" };
+        writeln!(f, "  type:     {}{}", self.column_type, nullable)?;
+        writeln!(f, "  values:   {} ({} empty)", self.count - self.nulls, self.nulls)?;
+        let (distinct, exact) = self.distinct.estimate();
+        writeln!(f, "  distinct: {}{}", if exact { "" } else { "~" }, distinct)?;
+        if self.column_type.is_numeric() {
+            if let (Some((_, min)), Some((_, max))) = (&self.numeric_min, &self.numeric_max) {
+                writeln!(f, "  range:    {} to {}", min, max)?;
+            }
+        } else if let (Some(min), Some(max)) = (&self.min, &self.max) {
+            writeln!(f, "  range:    {} to {}", min, max)?;
+        }
+        // The mean of a string column isn't going to tell anyone anything
+        if self.column_type.is_numeric() {
+            writeln!(f, "  mean:     {:.4} (stddev {:.4})", self.mean, self.stddev())?;
+        }
+        write!(f, "  samples:  {}", self.samples.join(", "))
+    }
+}
+
+fn profile<R: Read>(reader: R, samples: usize) -> csv::Result<Vec<ColumnProfile>> {
+    let mut rdr = csv::Reader::from_reader(reader);
+    let mut profiles: Vec<ColumnProfile> = rdr
+        .headers()?
+        .iter()
+        .map(|header| ColumnProfile::new(header, samples))
+        .collect();
+    let mut record = csv::StringRecord::new();
+    while rdr.read_record(&mut record)? {
+        for (profile, cell) in profiles.iter_mut().zip(record.iter()) {
+            profile.add(cell);
+        }
+    }
+    Ok(profiles)
+}
+
+// Generates the code we'd otherwise write by hand, like Planet in serde_csv.rs
+fn rust_struct(name: &str, profiles: &[ColumnProfile]) -> String {
+    let mut code = String::from("#[derive(Debug, Serialize, Deserialize)]\n");
+    code.push_str(&format!("struct {} {{\n", name));
+    let mut used = HashSet::new();
+    for profile in profiles {
+        let mut field = to_snake_case(&profile.name);
+        // Two headers can end up with the same field name, like "Name" and "name"
+        while !used.insert(field.clone()) {
+            field.push('_');
+        }
+        // Serde already strips the r# of raw identifiers
+        if field.trim_start_matches("r#") != profile.name {
+            code.push_str(&format!("    #[serde(rename = {:?})]\n", profile.name));
+        }
+        if profile.column_type == ColumnType::Date {
+            code.push_str("    // A date like 2021-12-24\n");
+        }
+        let rust_type = profile.column_type.rust_type();
+        // Columns without a single value could be anything
+        if profile.is_nullable() || profile.column_type == ColumnType::Unknown {
+            code.push_str(&format!("    {}: Option<{}>,\n", field, rust_type));
+        } else {
+            code.push_str(&format!("    {}: {},\n", field, rust_type));
+        }
+    }
+    code.push('}');
+    code
+}
+
+// Strict and reserved keywords of every edition up to 2024
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn", "else", "enum",
+    "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in", "let", "loop", "macro", "match", "mod",
+    "move", "mut", "override", "priv", "pub", "ref", "return", "static", "struct", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+// Keywords that can't be used as raw identifiers either
+const PATH_KEYWORDS: &[&str] = &["crate", "self", "Self", "super"];
+
+// "Distance from Sun (AU)" becomes distance_from_sun_au
+fn to_snake_case(header: &str) -> String {
+    let mut field = String::new();
+    let mut previous_lowercase = false;
+    for c in header.chars() {
+        if c.is_alphanumeric() {
+            if c.is_uppercase() && previous_lowercase {
+                field.push('_');
+            }
+            field.extend(c.to_lowercase());
+            previous_lowercase = c.is_lowercase() || c.is_numeric();
+        } else {
+            if !field.ends_with('_') && !field.is_empty() {
+                field.push('_');
+            }
+            previous_lowercase = false;
+        }
+    }
+    let mut field = field.trim_end_matches('_').to_string();
+    if field.is_empty() || field.starts_with(|c: char| c.is_numeric()) {
+        field.insert_str(0, "field_");
+    }
+    if KEYWORDS.contains(&field.as_str()) {
+        field.insert_str(0, "r#");
+    } else if PATH_KEYWORDS.contains(&field.as_str()) {
+        field.push('_');
+    }
+    field
+}
+
+fn to_camel_case(name: &str) -> String {
+    let camel: String = to_snake_case(name)
+        .trim_start_matches("r#")
+        .split('_')
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        })
+        .collect();
+    match camel.as_str() {
+        "" => "Record".to_string(),
+        // The only keyword that survives the capitalization
+        "Self" => "SelfRecord".to_string(),
+        _ => camel,
+    }
+}
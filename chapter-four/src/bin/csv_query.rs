@@ -0,0 +1,1035 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Write};
+use std::{env, error, fmt, process, result};
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if !args.is_empty() {
+        // Everything on the command line is one query, so quoting it is optional
+        let result = Query::parse(&args.join(" ")).and_then(|query| query.execute());
+        if let Err(e) = result {
+            eprintln!("Error: {}", e);
+            if let QueryError::Syntax(_) = e {
+                eprintln!(
+                    "\nUsage: csv_query \"SELECT <columns> FROM <file> [WHERE <condition>] \
+                     [GROUP BY <columns>] [ORDER BY <column> [ASC|DESC]] [LIMIT <n>]\""
+                );
+                process::exit(2);
+            }
+            process::exit(1);
+        }
+        return;
+    }
+
+    // Without a query, show off a couple on the planets written by csv.rs
+    let queries = [
+        "SELECT name, radius FROM solar_system_compared_to_earth.csv WHERE radius > 1 ORDER BY radius DESC LIMIT 3",
+        "SELECT name FROM solar_system_compared_to_earth.csv WHERE name >= 'M' AND NOT (gravity < 1 OR name = 'Neptune')",
+        "SELECT gravity, count(*), min(name) FROM solar_system_compared_to_earth.csv GROUP BY gravity ORDER BY count(*) DESC, gravity LIMIT 2",
+        "SELECT count(*), sum(radius), avg(gravity), max(distance_from_sun) FROM solar_system_compared_to_earth.csv",
+    ];
+    for query in &queries {
+        println!("> {}", query);
+        Query::parse(query)
+            .and_then(|query| query.execute())
+            .expect("Failed to run query");
+        println!();
+    }
+
+    // The same kind of query, put together in code instead of parsed
+    println!("> The inner planets with the builder API");
+    Query::new("solar_system_compared_to_earth.csv")
+        .select("name")
+        .select("distance_from_sun")
+        .filter(Condition::compare("distance_from_sun", Operator::LessThan, 2.0))
+        .order_by("distance_from_sun", Direction::Descending)
+        .execute()
+        .expect("Failed to run query");
+    println!();
+
+    println!("> Broken queries are reported, not panicked about");
+    let broken = [
+        "SELECT name FROM solar_system_compared_to_earth.csv WHERE moons > 2",
+        "SELECT name, count(*) FROM solar_system_compared_to_earth.csv",
+        "SELECT sum(name) FROM solar_system_compared_to_earth.csv",
+        "SELECT name FROM solar_system_compared_to_earth.csv WHERE radius >",
+    ];
+    for query in &broken {
+        match Query::parse(query).and_then(|query| query.execute()) {
+            Ok(_) => println!("'{}' unexpectedly worked", query),
+            Err(e) => println!("'{}': {}", query, e),
+        }
+    }
+}
+
+// Our custom error, following the pattern from chapter six
+#[derive(Debug)]
+enum QueryError {
+    Io(io::Error),
+    Csv(csv::Error),
+    // The query string itself is malformed
+    Syntax(String),
+    // The query is well formed, but doesn't fit the data
+    Invalid(String),
+}
+
+type Result<T> = result::Result<T, QueryError>;
+
+impl error::Error for QueryError {
+    fn cause(&self) -> Option<&dyn error::Error> {
+        match *self {
+            QueryError::Io(ref err) => Some(err),
+            QueryError::Csv(ref err) => Some(err),
+            QueryError::Syntax(_) | QueryError::Invalid(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            QueryError::Io(ref err) => write!(f, "IO error: {}", err),
+            QueryError::Csv(ref err) => write!(f, "CSV error: {}", err),
+            QueryError::Syntax(ref message) => write!(f, "Syntax error: {}", message),
+            QueryError::Invalid(ref message) => write!(f, "Invalid query: {}", message),
+        }
+    }
+}
+
+impl From<io::Error> for QueryError {
+    fn from(err: io::Error) -> QueryError {
+        QueryError::Io(err)
+    }
+}
+
+impl From<csv::Error> for QueryError {
+    fn from(err: csv::Error) -> QueryError {
+        QueryError::Csv(err)
+    }
+}
+
+// A single value, typed by looking at it.
+// Empty cells are null, just like missing values in SQL
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Null,
+    Number(f64),
+    Text(String),
+}
+
+impl Value {
+    fn parse(cell: &str) -> Value {
+        if cell.is_empty() {
+            return Value::Null;
+        }
+        match cell.parse::<f64>() {
+            Ok(number) if number.is_finite() => Value::Number(number),
+            _ => Value::Text(cell.to_string()),
+        }
+    }
+
+    // Nulls come first, then numbers, then text
+    fn sort_cmp(&self, other: &Value) -> Ordering {
+        match (self, other) {
+            (Value::Null, Value::Null) => Ordering::Equal,
+            (Value::Null, _) => Ordering::Less,
+            (_, Value::Null) => Ordering::Greater,
+            (Value::Number(a), Value::Number(b)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+            (Value::Number(_), Value::Text(_)) => Ordering::Less,
+            (Value::Text(_), Value::Number(_)) => Ordering::Greater,
+            (Value::Text(a), Value::Text(b)) => a.cmp(b),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::Null => Ok(()),
+            Value::Number(number) => write!(f, "{}", number),
+            Value::Text(ref text) => write!(f, "{}", text),
+        }
+    }
+}
+
+impl From<f64> for Value {
+    fn from(number: f64) -> Value {
+        Value::Number(number)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(text: &str) -> Value {
+        Value::Text(text.to_string())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operator {
+    Equal,
+    NotEqual,
+    LessThan,
+    LessOrEqual,
+    GreaterThan,
+    GreaterOrEqual,
+}
+
+impl Operator {
+    fn from_symbol(symbol: &str) -> Option<Operator> {
+        match symbol {
+            "=" => Some(Operator::Equal),
+            "!=" | "<>" => Some(Operator::NotEqual),
+            "<" => Some(Operator::LessThan),
+            "<=" => Some(Operator::LessOrEqual),
+            ">" => Some(Operator::GreaterThan),
+            ">=" => Some(Operator::GreaterOrEqual),
+            _ => None,
+        }
+    }
+
+    // Comparisons only make sense between values of the same type.
+    // Like in SQL, anything compared to null, or a number compared
+    // to text, is simply not a match
+    fn matches(self, left: &Value, right: &Value) -> bool {
+        let ordering = match (left, right) {
+            (Value::Number(a), Value::Number(b)) => a.partial_cmp(b),
+            (Value::Text(a), Value::Text(b)) => Some(a.cmp(b)),
+            _ => None,
+        };
+        match ordering {
+            Some(ordering) => match self {
+                Operator::Equal => ordering == Ordering::Equal,
+                Operator::NotEqual => ordering != Ordering::Equal,
+                Operator::LessThan => ordering == Ordering::Less,
+                Operator::LessOrEqual => ordering != Ordering::Greater,
+                Operator::GreaterThan => ordering == Ordering::Greater,
+                Operator::GreaterOrEqual => ordering != Ordering::Less,
+            },
+            None => false,
+        }
+    }
+}
+
+// The WHERE part of a query
+#[derive(Debug, Clone)]
+enum Condition {
+    Compare {
+        column: String,
+        operator: Operator,
+        value: Value,
+    },
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>),
+    Not(Box<Condition>),
+}
+
+impl Condition {
+    fn compare<V: Into<Value>>(column: &str, operator: Operator, value: V) -> Condition {
+        Condition::Compare {
+            column: column.to_string(),
+            operator,
+            value: value.into(),
+        }
+    }
+
+    fn and(self, other: Condition) -> Condition {
+        Condition::And(Box::new(self), Box::new(other))
+    }
+
+    fn or(self, other: Condition) -> Condition {
+        Condition::Or(Box::new(self), Box::new(other))
+    }
+
+    // Swap the column names for their positions, so we don't
+    // have to look them up again for every single record
+    fn resolve(&self, headers: &csv::StringRecord) -> Result<Filter> {
+        Ok(match self {
+            Condition::Compare { column, operator, value } => Filter::Compare {
+                index: column_index(headers, column)?,
+                operator: *operator,
+                value: value.clone(),
+            },
+            Condition::And(left, right) => {
+                Filter::And(Box::new(left.resolve(headers)?), Box::new(right.resolve(headers)?))
+            }
+            Condition::Or(left, right) => {
+                Filter::Or(Box::new(left.resolve(headers)?), Box::new(right.resolve(headers)?))
+            }
+            Condition::Not(inner) => Filter::Not(Box::new(inner.resolve(headers)?)),
+        })
+    }
+}
+
+enum Filter {
+    Compare {
+        index: usize,
+        operator: Operator,
+        value: Value,
+    },
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+    Not(Box<Filter>),
+}
+
+impl Filter {
+    fn matches(&self, record: &csv::StringRecord) -> bool {
+        match self {
+            Filter::Compare { index, operator, value } => {
+                operator.matches(&Value::parse(&record[*index]), value)
+            }
+            Filter::And(left, right) => left.matches(record) && right.matches(record),
+            Filter::Or(left, right) => left.matches(record) || right.matches(record),
+            Filter::Not(inner) => !inner.matches(record),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Function {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Function> {
+        match name.to_ascii_lowercase().as_str() {
+            "count" => Some(Function::Count),
+            "sum" => Some(Function::Sum),
+            "avg" => Some(Function::Avg),
+            "min" => Some(Function::Min),
+            "max" => Some(Function::Max),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Function::Count => "count",
+            Function::Sum => "sum",
+            Function::Avg => "avg",
+            Function::Min => "min",
+            Function::Max => "max",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// One entry of the SELECT list
+#[derive(Debug, Clone)]
+enum Selection {
+    All,
+    Column(String),
+    // A column of None stands for count(*)
+    Aggregate(Function, Option<String>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Direction {
+    Ascending,
+    Descending,
+}
+
+// A query, put together either by Query::parse or by hand
+#[derive(Debug, Clone)]
+struct Query {
+    source: String,
+    selections: Vec<Selection>,
+    condition: Option<Condition>,
+    group_by: Vec<String>,
+    order_by: Vec<(String, Direction)>,
+    limit: Option<usize>,
+}
+
+impl Query {
+    fn new(source: &str) -> Query {
+        Query {
+            source: source.to_string(),
+            selections: Vec::new(),
+            condition: None,
+            group_by: Vec::new(),
+            order_by: Vec::new(),
+            limit: None,
+        }
+    }
+
+    fn select(mut self, column: &str) -> Query {
+        self.selections.push(if column == "*" {
+            Selection::All
+        } else {
+            Selection::Column(column.to_string())
+        });
+        self
+    }
+
+    fn aggregate(mut self, function: Function, column: Option<&str>) -> Query {
+        self.selections
+            .push(Selection::Aggregate(function, column.map(str::to_string)));
+        self
+    }
+
+    // Calling this more than once combines the conditions with AND
+    fn filter(mut self, condition: Condition) -> Query {
+        self.condition = Some(match self.condition.take() {
+            Some(existing) => existing.and(condition),
+            None => condition,
+        });
+        self
+    }
+
+    fn group_by(mut self, column: &str) -> Query {
+        self.group_by.push(column.to_string());
+        self
+    }
+
+    fn order_by(mut self, column: &str, direction: Direction) -> Query {
+        self.order_by.push((column.to_string(), direction));
+        self
+    }
+
+    fn limit(mut self, limit: usize) -> Query {
+        self.limit = Some(limit);
+        self
+    }
+
+    fn parse(query: &str) -> Result<Query> {
+        Parser::new(query)?.parse_query()
+    }
+
+    // Run the query against its source file and print the result to stdout
+    fn execute(&self) -> Result<usize> {
+        let file = File::open(&self.source)?;
+        let stdout = io::stdout();
+        self.run(BufReader::new(file), stdout.lock())
+    }
+
+    // Returns the number of rows written
+    fn run<R: Read, W: Write>(&self, reader: R, writer: W) -> Result<usize> {
+        let mut rdr = csv::Reader::from_reader(reader);
+        let headers = rdr.headers()?.clone();
+        let plan = Plan::new(self, &headers)?;
+        let mut wtr = csv::Writer::from_writer(writer);
+        // Rows that need sorting or aggregating only go out at the very end,
+        // so we hold the header back as well in case something fails on the way
+        let streaming = !plan.is_aggregate && plan.order.is_empty();
+        if streaming {
+            wtr.write_record(&plan.output_names)?;
+        }
+
+        let mut sorter = Sorter::new(plan.order.clone(), self.limit);
+        let mut groups = Groups::default();
+        let mut written = 0;
+        let mut record = csv::StringRecord::new();
+        while rdr.read_record(&mut record)? {
+            if let Some(filter) = &plan.filter {
+                if !filter.matches(&record) {
+                    continue;
+                }
+            }
+            if plan.is_aggregate {
+                groups.add(&plan, &record)?;
+            } else if streaming {
+                // Nothing to sort, so rows go out as soon as we see them
+                // and we can stop reading once we have enough
+                if self.limit == Some(written) {
+                    break;
+                }
+                wtr.write_record(plan.project(&record).iter().map(Value::to_string))?;
+                written += 1;
+            } else {
+                let key = plan.order.iter().map(|(index, _)| Value::parse(&record[*index])).collect();
+                sorter.push(key, plan.project(&record));
+            }
+        }
+
+        if plan.is_aggregate {
+            for row in groups.finish(&plan) {
+                let key = plan.order.iter().map(|(index, _)| row[*index].clone()).collect();
+                sorter.push(key, row);
+            }
+        }
+        if !streaming {
+            wtr.write_record(&plan.output_names)?;
+        }
+        for row in sorter.finish() {
+            wtr.write_record(row.iter().map(Value::to_string))?;
+            written += 1;
+        }
+        wtr.flush()?;
+        Ok(written)
+    }
+}
+
+fn column_index(headers: &csv::StringRecord, column: &str) -> Result<usize> {
+    headers
+        .iter()
+        .position(|header| header == column)
+        .ok_or_else(|| QueryError::Invalid(format!("Unknown column '{}'", column)))
+}
+
+// What a selection turns into once the columns are known
+#[derive(Debug, Clone, Copy)]
+enum Output {
+    Column(usize),
+    // An index into the group key
+    GroupColumn(usize),
+    // An index into the aggregates of a group
+    Aggregate(usize),
+}
+
+// A query checked against the actual headers of a file
+struct Plan {
+    filter: Option<Filter>,
+    outputs: Vec<Output>,
+    output_names: Vec<String>,
+    is_aggregate: bool,
+    group_indices: Vec<usize>,
+    // The functions to compute for every group, the column they read and its name
+    aggregates: Vec<(Function, Option<usize>, String)>,
+    // Indices into the input record, or into the output row for aggregate queries
+    order: Vec<(usize, Direction)>,
+}
+
+impl Plan {
+    fn new(query: &Query, headers: &csv::StringRecord) -> Result<Plan> {
+        let group_indices = query
+            .group_by
+            .iter()
+            .map(|column| column_index(headers, column))
+            .collect::<Result<Vec<_>>>()?;
+        let is_aggregate = !group_indices.is_empty()
+            || query.selections.iter().any(|selection| matches!(selection, Selection::Aggregate(..)));
+
+        let mut outputs = Vec::new();
+        let mut output_names = Vec::new();
+        let mut aggregates = Vec::new();
+        // SELECT without any columns is the same as SELECT *
+        let all = [Selection::All];
+        let selections = if query.selections.is_empty() { &all[..] } else { &query.selections[..] };
+        for selection in selections {
+            match selection {
+                Selection::All if is_aggregate => {
+                    return Err(QueryError::Invalid("* can't be combined with aggregates".to_string()))
+                }
+                Selection::All => {
+                    outputs.extend((0..headers.len()).map(Output::Column));
+                    output_names.extend(headers.iter().map(str::to_string));
+                }
+                Selection::Column(column) => {
+                    let index = column_index(headers, column)?;
+                    if is_aggregate {
+                        // Every other column has more than one value per group
+                        let position = group_indices.iter().position(|&group| group == index);
+                        let position = position.ok_or_else(|| {
+                            QueryError::Invalid(format!(
+                                "'{}' has to be aggregated or be part of GROUP BY",
+                                column
+                            ))
+                        })?;
+                        outputs.push(Output::GroupColumn(position));
+                    } else {
+                        outputs.push(Output::Column(index));
+                    }
+                    output_names.push(column.clone());
+                }
+                Selection::Aggregate(function, column) => {
+                    let index = column.as_deref().map(|column| column_index(headers, column)).transpose()?;
+                    if index.is_none() && *function != Function::Count {
+                        return Err(QueryError::Invalid(format!("{}(*) isn't supported, name a column", function)));
+                    }
+                    outputs.push(Output::Aggregate(aggregates.len()));
+                    aggregates.push((*function, index, column.clone().unwrap_or_else(|| "*".to_string())));
+                    output_names.push(format!("{}({})", function, column.as_deref().unwrap_or("*")));
+                }
+            }
+        }
+
+        // Aggregate queries are sorted by what they output, others by any input column
+        let order = query
+            .order_by
+            .iter()
+            .map(|(column, direction)| {
+                let index = if is_aggregate {
+                    output_names.iter().position(|name| name == column).ok_or_else(|| {
+                        QueryError::Invalid(format!("ORDER BY '{}' has to name a selected column", column))
+                    })?
+                } else {
+                    column_index(headers, column)?
+                };
+                Ok((index, *direction))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Plan {
+            filter: query.condition.as_ref().map(|condition| condition.resolve(headers)).transpose()?,
+            outputs,
+            output_names,
+            is_aggregate,
+            group_indices,
+            aggregates,
+            order,
+        })
+    }
+
+    fn project(&self, record: &csv::StringRecord) -> Vec<Value> {
+        self.outputs
+            .iter()
+            .map(|output| match output {
+                Output::Column(index) => Value::parse(&record[*index]),
+                _ => unreachable!("Only aggregate queries have group columns or aggregates"),
+            })
+            .collect()
+    }
+}
+
+// The running state of a single aggregate function
+#[derive(Debug, Clone)]
+struct Accumulator {
+    count: u64,
+    sum: f64,
+    min: Option<Value>,
+    max: Option<Value>,
+}
+
+impl Accumulator {
+    fn new() -> Accumulator {
+        Accumulator {
+            count: 0,
+            sum: 0.0,
+            min: None,
+            max: None,
+        }
+    }
+
+    fn add(&mut self, function: Function, value: Value, column: &str) -> Result<()> {
+        // Nulls are skipped by every function, count(*) passes in a dummy value
+        if value == Value::Null {
+            return Ok(());
+        }
+        if let Function::Sum | Function::Avg = function {
+            match value {
+                Value::Number(number) => self.sum += number,
+                _ => {
+                    return Err(QueryError::Invalid(format!(
+                        "Can't compute {}({}), '{}' is not a number",
+                        function, column, value
+                    )))
+                }
+            }
+        }
+        self.count += 1;
+        if self.min.as_ref().is_none_or(|min| value.sort_cmp(min) == Ordering::Less) {
+            self.min = Some(value.clone());
+        }
+        if self.max.as_ref().is_none_or(|max| value.sort_cmp(max) == Ordering::Greater) {
+            self.max = Some(value);
+        }
+        Ok(())
+    }
+
+    fn finish(&self, function: Function) -> Value {
+        match function {
+            Function::Count => Value::Number(self.count as f64),
+            Function::Sum => Value::Number(self.sum),
+            Function::Avg if self.count == 0 => Value::Null,
+            Function::Avg => Value::Number(self.sum / self.count as f64),
+            Function::Min => self.min.clone().unwrap_or(Value::Null),
+            Function::Max => self.max.clone().unwrap_or(Value::Null),
+        }
+    }
+}
+
+// One set of accumulators per group. Memory grows with the
+// number of groups, not with the number of records
+#[derive(Default)]
+struct Groups {
+    positions: HashMap<Vec<String>, usize>,
+    // Kept in the order the groups first showed up in
+    groups: Vec<(Vec<String>, Vec<Accumulator>)>,
+}
+
+impl Groups {
+    fn add(&mut self, plan: &Plan, record: &csv::StringRecord) -> Result<()> {
+        let key: Vec<String> = plan.group_indices.iter().map(|&index| record[index].to_string()).collect();
+        let position = match self.positions.get(&key) {
+            Some(&position) => position,
+            None => {
+                self.groups.push((key.clone(), vec![Accumulator::new(); plan.aggregates.len()]));
+                self.positions.insert(key, self.groups.len() - 1);
+                self.groups.len() - 1
+            }
+        };
+        let accumulators = &mut self.groups[position].1;
+        for ((function, index, column), accumulator) in plan.aggregates.iter().zip(accumulators) {
+            let value = match index {
+                Some(index) => Value::parse(&record[*index]),
+                None => Value::Number(1.0),
+            };
+            accumulator.add(*function, value, column)?;
+        }
+        Ok(())
+    }
+
+    fn finish(self, plan: &Plan) -> Vec<Vec<Value>> {
+        // Aggregating without GROUP BY still gives one row, even with no records
+        let groups = if self.groups.is_empty() && plan.group_indices.is_empty() {
+            vec![(Vec::new(), vec![Accumulator::new(); plan.aggregates.len()])]
+        } else {
+            self.groups
+        };
+        groups
+            .into_iter()
+            .map(|(key, accumulators)| {
+                plan.outputs
+                    .iter()
+                    .map(|output| match output {
+                        Output::GroupColumn(position) => Value::parse(&key[*position]),
+                        Output::Aggregate(position) => accumulators[*position].finish(plan.aggregates[*position].0),
+                        Output::Column(_) => unreachable!("Aggregate queries only output groups and aggregates"),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+// Collects rows for ORDER BY. With a LIMIT, we only ever need to
+// remember the best `limit` rows, so every now and then the rest is dropped
+struct Sorter {
+    order: Vec<(usize, Direction)>,
+    limit: Option<usize>,
+    rows: Vec<(Vec<Value>, Vec<Value>)>,
+}
+
+impl Sorter {
+    fn new(order: Vec<(usize, Direction)>, limit: Option<usize>) -> Sorter {
+        Sorter {
+            order,
+            limit,
+            rows: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, key: Vec<Value>, row: Vec<Value>) {
+        self.rows.push((key, row));
+        if let Some(limit) = self.limit {
+            if self.rows.len() >= limit.max(512).saturating_mul(2) {
+                self.sort_and_truncate(limit);
+            }
+        }
+    }
+
+    fn sort_and_truncate(&mut self, limit: usize) {
+        let order = &self.order;
+        // A stable sort keeps rows with equal keys in file order
+        self.rows.sort_by(|(a, _), (b, _)| {
+            order
+                .iter()
+                .zip(a.iter().zip(b))
+                .map(|((_, direction), (a, b))| match direction {
+                    Direction::Ascending => a.sort_cmp(b),
+                    Direction::Descending => b.sort_cmp(a),
+                })
+                .find(|ordering| *ordering != Ordering::Equal)
+                .unwrap_or(Ordering::Equal)
+        });
+        self.rows.truncate(limit);
+    }
+
+    fn finish(mut self) -> Vec<Vec<Value>> {
+        self.sort_and_truncate(self.limit.unwrap_or(usize::MAX));
+        self.rows.into_iter().map(|(_, row)| row).collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    // Kept as written, so LIMIT can parse it as a row count
+    Number(String),
+    Text(String),
+    Symbol(String),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Word(word) => write!(f, "'{}'", word),
+            Token::Number(number) => write!(f, "{}", number),
+            Token::Text(text) => write!(f, "'{}'", text.replace('\'', "''")),
+            Token::Symbol(symbol) => write!(f, "'{}'", symbol),
+        }
+    }
+}
+
+fn tokenize(query: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = query.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '\'' {
+            // Text in single quotes, with '' standing for a quote
+            chars.next();
+            let mut text = String::new();
+            loop {
+                match chars.next() {
+                    Some('\'') if chars.peek() == Some(&'\'') => {
+                        chars.next();
+                        text.push('\'');
+                    }
+                    Some('\'') => break,
+                    Some(c) => text.push(c),
+                    None => return Err(QueryError::Syntax("Unterminated text".to_string())),
+                }
+            }
+            tokens.push(Token::Text(text));
+        } else if c.is_ascii_digit() || c == '-' {
+            let mut number = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_ascii_digit() || c == '.' || (c == '-' && number.is_empty()) {
+                    number.push(c);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            if number.parse::<f64>().is_err() {
+                return Err(QueryError::Syntax(format!("Invalid number '{}'", number)));
+            }
+            tokens.push(Token::Number(number));
+        } else if c.is_alphabetic() || c == '_' || c == '.' || c == '/' {
+            // File names are words too, so they may contain dots and slashes
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_alphanumeric() || "_./-".contains(c) {
+                    word.push(c);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            tokens.push(Token::Word(word));
+        } else {
+            chars.next();
+            let mut symbol = c.to_string();
+            if let Some(&next) = chars.peek() {
+                let pair = format!("{}{}", c, next);
+                if ["<=", ">=", "!=", "<>"].contains(&pair.as_str()) {
+                    chars.next();
+                    symbol = pair;
+                }
+            }
+            if !["(", ")", ",", "*", "=", "<", ">", "<=", ">=", "!=", "<>"].contains(&symbol.as_str()) {
+                return Err(QueryError::Syntax(format!("Unexpected character '{}'", symbol)));
+            }
+            tokens.push(Token::Symbol(symbol));
+        }
+    }
+    Ok(tokens)
+}
+
+// A small recursive descent parser that feeds the builder
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn new(query: &str) -> Result<Parser> {
+        Ok(Parser {
+            tokens: tokenize(query)?,
+            position: 0,
+        })
+    }
+
+    fn parse_query(&mut self) -> Result<Query> {
+        self.expect_keyword("SELECT")?;
+        let mut selections = Vec::new();
+        loop {
+            selections.push(self.parse_selection()?);
+            if !self.symbol(",") {
+                break;
+            }
+        }
+        self.expect_keyword("FROM")?;
+        let mut query = Query::new(&self.identifier()?);
+        for selection in selections {
+            query = match selection {
+                Selection::All => query.select("*"),
+                Selection::Column(column) => query.select(&column),
+                Selection::Aggregate(function, column) => query.aggregate(function, column.as_deref()),
+            };
+        }
+        if self.keyword("WHERE") {
+            query = query.filter(self.parse_or()?);
+        }
+        if self.keyword("GROUP") {
+            self.expect_keyword("BY")?;
+            loop {
+                query = query.group_by(&self.identifier()?);
+                if !self.symbol(",") {
+                    break;
+                }
+            }
+        }
+        if self.keyword("ORDER") {
+            self.expect_keyword("BY")?;
+            loop {
+                let column = match self.parse_selection()? {
+                    Selection::Column(column) => column,
+                    Selection::Aggregate(function, column) => {
+                        format!("{}({})", function, column.as_deref().unwrap_or("*"))
+                    }
+                    Selection::All => return Err(QueryError::Syntax("Can't order by *".to_string())),
+                };
+                let direction = if self.keyword("DESC") {
+                    Direction::Descending
+                } else {
+                    self.keyword("ASC");
+                    Direction::Ascending
+                };
+                query = query.order_by(&column, direction);
+                if !self.symbol(",") {
+                    break;
+                }
+            }
+        }
+        if self.keyword("LIMIT") {
+            match self.next() {
+                Some(Token::Number(number)) => {
+                    let limit = number
+                        .parse()
+                        .map_err(|_| QueryError::Syntax(format!("Invalid row count '{}'", number)))?;
+                    query = query.limit(limit)
+                }
+                other => return Err(unexpected(other, "a row count")),
+            }
+        }
+        if let Some(token) = self.next() {
+            return Err(unexpected(Some(token), "the end of the query"));
+        }
+        Ok(query)
+    }
+
+    fn parse_selection(&mut self) -> Result<Selection> {
+        if self.symbol("*") {
+            return Ok(Selection::All);
+        }
+        let name = self.identifier()?;
+        if !self.symbol("(") {
+            return Ok(Selection::Column(name));
+        }
+        let function = Function::from_name(&name)
+            .ok_or_else(|| QueryError::Syntax(format!("Unknown function '{}'", name)))?;
+        let column = if self.symbol("*") { None } else { Some(self.identifier()?) };
+        self.expect_symbol(")")?;
+        Ok(Selection::Aggregate(function, column))
+    }
+
+    // OR binds weaker than AND, just like in SQL
+    fn parse_or(&mut self) -> Result<Condition> {
+        let mut condition = self.parse_and()?;
+        while self.keyword("OR") {
+            condition = condition.or(self.parse_and()?);
+        }
+        Ok(condition)
+    }
+
+    fn parse_and(&mut self) -> Result<Condition> {
+        let mut condition = self.parse_comparison()?;
+        while self.keyword("AND") {
+            condition = condition.and(self.parse_comparison()?);
+        }
+        Ok(condition)
+    }
+
+    fn parse_comparison(&mut self) -> Result<Condition> {
+        if self.keyword("NOT") {
+            return Ok(Condition::Not(Box::new(self.parse_comparison()?)));
+        }
+        if self.symbol("(") {
+            let condition = self.parse_or()?;
+            self.expect_symbol(")")?;
+            return Ok(condition);
+        }
+        let column = self.identifier()?;
+        let operator = match self.next() {
+            Some(Token::Symbol(symbol)) if Operator::from_symbol(&symbol).is_some() => {
+                Operator::from_symbol(&symbol).expect("Checked by the guard")
+            }
+            other => return Err(unexpected(other, "a comparison like '=' or '<'")),
+        };
+        let value = match self.next() {
+            Some(Token::Number(number)) => {
+                Value::Number(number.parse().expect("Checked by the tokenizer"))
+            }
+            Some(Token::Text(text)) => Value::Text(text),
+            other => return Err(unexpected(other, "a number or 'text'")),
+        };
+        Ok(Condition::compare(&column, operator, value))
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    // Consumes the keyword if it comes next
+    fn keyword(&mut self, keyword: &str) -> bool {
+        match self.tokens.get(self.position) {
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword) => {
+                self.position += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<()> {
+        if self.keyword(keyword) {
+            Ok(())
+        } else {
+            Err(unexpected(self.tokens.get(self.position).cloned(), keyword))
+        }
+    }
+
+    fn symbol(&mut self, symbol: &str) -> bool {
+        match self.tokens.get(self.position) {
+            Some(Token::Symbol(next)) if next == symbol => {
+                self.position += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<()> {
+        if self.symbol(symbol) {
+            Ok(())
+        } else {
+            Err(unexpected(self.tokens.get(self.position).cloned(), &format!("'{}'", symbol)))
+        }
+    }
+
+    fn identifier(&mut self) -> Result<String> {
+        match self.next() {
+            Some(Token::Word(word)) => Ok(word),
+            other => Err(unexpected(other, "a name")),
+        }
+    }
+}
+
+fn unexpected(found: Option<Token>, expected: &str) -> QueryError {
+    match found {
+        Some(token) => QueryError::Syntax(format!("Expected {}, found {}", expected, token)),
+        None => QueryError::Syntax(format!("Expected {}, but the query ended", expected)),
+    }
+}
@@ -1,33 +1,86 @@
-use serde_json::json;
+use serde_json::{json, Value};
 use std::io::{self, BufRead};
-use std::collections::HashMap;
 
 fn main() {
-    // A HashMap is the same as a JSON without any schema
-    let mut key_value_map = HashMap::new();
+    // A Value is the same as a JSON without any schema
+    let mut document = json!({});
     let stdin = io::stdin();
-    println!("Enter a key and a value");
+    println!("Enter a key and a value, nested keys are separated by dots");
     for input in stdin.lock().lines() {
         let input = input.expect("Failed to read line");
-        let key_value: Vec<_> = input.split_whitespace().collect();
-        let key = key_value[0].to_string();
-        let value = key_value[1].to_string();
+        // Everything after the key is the value, spaces included
+        let (key, value) = match input.trim().split_once(char::is_whitespace) {
+            Some((key, value)) => (key, parse_value(value.trim())),
+            None => {
+                println!("Please enter both a key and a value, like 'person.age 23'");
+                continue;
+            }
+        };
 
-        println!("Saving key-value pair: {} -> {}", key, value);
-        // The json! macro lets us convert a value into its JSON representation
-        key_value_map.insert(key, json!(value));
+        // Work on a copy, so a failed insert doesn't leave half a path behind
+        let mut edited = document.clone();
+        let shown = value.to_string();
+        match insert(&mut edited, key, value) {
+            Ok(()) => {
+                println!("Saving key-value pair: {} -> {}", key, shown);
+                document = edited;
+            }
+            Err(e) => println!("Failed to save {}: {}", key, e),
+        }
         println!(
             "Enter another pair or stop by pressing '{}'",
             END_OF_TRANSMISSION
         );
     }
     // to_string_pretty returns a JSON with nicely readable whitespace
-    let json =
-        serde_json::to_string_pretty(&key_value_map).expect("Failed to convert HashMap into JSON");
+    let json = serde_json::to_string_pretty(&document).expect("Failed to convert Value into JSON");
     println!("Your input has been made into the following JSON:");
     println!("{}", json);
 }
 
+// Anything that is valid JSON keeps its type, so 23 is a number,
+// true a bool and [1, 2] an array. Everything else becomes a string
+fn parse_value(input: &str) -> Value {
+    serde_json::from_str(input).unwrap_or_else(|_| json!(input))
+}
+
+// Insert a value at a path like "pets.0.name", creating objects on the way.
+// Numbers index into existing arrays, or append when they are one past the end
+fn insert(document: &mut Value, path: &str, value: Value) -> Result<(), String> {
+    let mut current = document;
+    for key in path.split('.') {
+        if key.is_empty() {
+            return Err(format!("'{}' contains an empty key", path));
+        }
+        current = match current {
+            Value::Array(array) => {
+                let index: usize = key
+                    .parse()
+                    .map_err(|_| format!("'{}' is not a valid array index", key))?;
+                if index == array.len() {
+                    array.push(Value::Null);
+                }
+                array
+                    .get_mut(index)
+                    .ok_or_else(|| format!("Index {} is out of bounds", index))?
+            }
+            // Null is what we just pushed, or a value that was never set
+            Value::Null => {
+                *current = json!({});
+                current
+                    .as_object_mut()
+                    .expect("We just made it an object")
+                    .entry(key)
+                    .or_insert(Value::Null)
+            }
+            Value::Object(object) => object.entry(key).or_insert(Value::Null),
+            other => return Err(format!("Can't add '{}' to {}, it's not an object", key, other)),
+        };
+    }
+    *current = value;
+    Ok(())
+}
+
 #[cfg(target_os = "windows")]
 const END_OF_TRANSMISSION: &str = "Ctrl Z";
 
@@ -0,0 +1,449 @@
+use serde_json::{json, Map, Number, Value};
+use std::path::Path;
+use std::{env, fmt, fs, process};
+
+fn main() {
+    let options = match Options::parse(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n", e);
+            eprintln!(
+                "Usage: json_diff [<old> <new>] [--ignore <pointer>]... [--patch] [--no-color]\n\
+                 \n\
+                 Compares two JSON or TOML documents, told apart by their extension.\n\
+                 Ignored pointers may use * for any key or index, like /pets/*/age.\n\
+                 Without files, pet_owner.json is compared to an edited copy of itself.\n\
+                 Exits with 0 if the documents are the same and with 1 if they differ."
+            );
+            process::exit(2);
+        }
+    };
+
+    let (old, new) = match &options.files {
+        Some((old, new)) => match (load(old), load(new)) {
+            (Ok(old), Ok(new)) => (old, new),
+            (Err(e), _) | (_, Err(e)) => {
+                eprintln!("Error: {}", e);
+                process::exit(2);
+            }
+        },
+        None => {
+            let old = load("pet_owner.json").expect("Failed to load pet_owner.json");
+            (old, edited_pet_owner())
+        }
+    };
+
+    let diff = Diff::new(&options.ignore).compare(&old, &new);
+    if options.patch {
+        println!("{}", serde_json::to_string_pretty(&diff.patch).expect("Values are always valid JSON"));
+    } else {
+        for change in &diff.changes {
+            println!("{}", change.colored(options.color));
+        }
+    }
+    if !diff.changes.is_empty() {
+        process::exit(1);
+    }
+}
+
+// pet_owner.json after a year has passed
+fn edited_pet_owner() -> Value {
+    json!({
+        "name": "John",
+        "age": 24,
+        "updated_at": "2021-12-24T12:00:00Z",
+        "pets": [
+            { "name": "Speedy", "species": "Turtle", "age": 48, "colour": "Green" },
+            { "name": "Meows", "species": "Cat", "age": null, "colour": "Orange" },
+            { "name": "Nemo", "species": "Fish", "age": 1, "colour": "Orange" },
+            { "name": "Waldo", "species": "Dog", "age": 2, "colour": null }
+        ]
+    })
+}
+
+struct Options {
+    files: Option<(String, String)>,
+    ignore: Vec<String>,
+    patch: bool,
+    color: bool,
+}
+
+impl Options {
+    fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+        // Respect https://no-color.org
+        let mut options = Options {
+            files: None,
+            ignore: Vec::new(),
+            patch: false,
+            color: env::var_os("NO_COLOR").is_none(),
+        };
+        let mut files = Vec::new();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--ignore" => options.ignore.push(args.next().ok_or("Missing value for --ignore")?),
+                "--patch" => options.patch = true,
+                "--no-color" => options.color = false,
+                flag if flag.starts_with('-') => return Err(format!("Unknown option '{}'", flag)),
+                file => files.push(file.to_string()),
+            }
+        }
+        options.files = match files.len() {
+            0 => None,
+            2 => Some((files.remove(0), files.remove(0))),
+            _ => return Err("Expected either no files or exactly two".to_string()),
+        };
+        Ok(options)
+    }
+}
+
+// TOML is compared by converting it to the same value model as JSON
+fn load(path: &str) -> Result<Value, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    if Path::new(path).extension().is_some_and(|extension| extension == "toml") {
+        let value: toml::Value = toml::from_str(&content).map_err(|e| format!("{} is not valid TOML: {}", path, e))?;
+        Ok(toml_to_json(value))
+    } else {
+        serde_json::from_str(&content).map_err(|e| format!("{} is not valid JSON: {}", path, e))
+    }
+}
+
+fn toml_to_json(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(string) => Value::String(string),
+        toml::Value::Integer(integer) => Value::from(integer),
+        // NaN and infinity have no JSON representation
+        toml::Value::Float(float) => Number::from_f64(float).map_or(Value::Null, Value::Number),
+        toml::Value::Boolean(boolean) => Value::Bool(boolean),
+        toml::Value::Datetime(datetime) => Value::String(datetime.to_string()),
+        toml::Value::Array(array) => Value::Array(array.into_iter().map(toml_to_json).collect()),
+        toml::Value::Table(table) => Value::Object(
+            table
+                .into_iter()
+                .map(|(key, value)| (key, toml_to_json(value)))
+                .collect(),
+        ),
+    }
+}
+
+// A JSON Pointer, kept as its separate tokens
+#[derive(Debug, Clone, PartialEq)]
+struct Pointer(Vec<String>);
+
+impl Pointer {
+    fn root() -> Pointer {
+        Pointer(Vec::new())
+    }
+
+    fn child<T: ToString>(&self, token: T) -> Pointer {
+        let mut tokens = self.0.clone();
+        tokens.push(token.to_string());
+        Pointer(tokens)
+    }
+
+    // A * in the pattern stands for any single key or index
+    fn matches(&self, pattern: &Pointer) -> bool {
+        self.0.len() == pattern.0.len()
+            && self
+                .0
+                .iter()
+                .zip(&pattern.0)
+                .all(|(token, pattern)| pattern == "*" || token == pattern)
+    }
+
+    fn parse(pointer: &str) -> Pointer {
+        if pointer.is_empty() {
+            return Pointer::root();
+        }
+        Pointer(
+            pointer
+                .trim_start_matches('/')
+                .split('/')
+                .map(|token| token.replace("~1", "/").replace("~0", "~"))
+                .collect(),
+        )
+    }
+}
+
+impl fmt::Display for Pointer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, "/");
+        }
+        for token in &self.0 {
+            write!(f, "/{}", token.replace('~', "~0").replace('/', "~1"))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+enum Change {
+    Added { path: Pointer, value: Value },
+    Removed { path: Pointer, value: Value },
+    Changed { path: Pointer, from: Value, to: Value },
+    // An array element that is still there, just somewhere else
+    Moved { from: Pointer, to: Pointer },
+}
+
+impl Change {
+    fn colored(&self, color: bool) -> String {
+        let (code, text) = match self {
+            Change::Added { path, value } => (32, format!("+ {}: {}", path, value)),
+            Change::Removed { path, value } => (31, format!("- {}: {}", path, value)),
+            Change::Changed { path, from, to } => (33, format!("~ {}: {} -> {}", path, from, to)),
+            Change::Moved { from, to } => (36, format!("> {} moved to {}", from, to)),
+        };
+        if color {
+            format!("\x1b[{}m{}\x1b[0m", code, text)
+        } else {
+            text
+        }
+    }
+}
+
+// Both ways of describing the differences, built in one go
+struct DiffResult {
+    // For people, with paths into the new document
+    changes: Vec<Change>,
+    // For programs, an RFC 6902 JSON Patch that turns old into new
+    patch: Vec<Value>,
+}
+
+struct Diff {
+    ignore: Vec<Pointer>,
+}
+
+impl Diff {
+    fn new(ignore: &[String]) -> Diff {
+        Diff {
+            ignore: ignore.iter().map(|pointer| Pointer::parse(pointer)).collect(),
+        }
+    }
+
+    fn compare(&self, old: &Value, new: &Value) -> DiffResult {
+        let mut result = DiffResult {
+            changes: Vec::new(),
+            patch: Vec::new(),
+        };
+        self.diff(old, new, &Pointer::root(), &Pointer::root(), &mut result);
+        result
+    }
+
+    fn is_ignored(&self, path: &Pointer) -> bool {
+        self.ignore.iter().any(|pattern| path.matches(pattern))
+    }
+
+    // Array elements can have a different index in the old and the new document.
+    // The patch needs the old one, since it's applied to the old document, but
+    // people want to see where things ended up
+    fn diff(&self, old: &Value, new: &Value, old_path: &Pointer, new_path: &Pointer, result: &mut DiffResult) {
+        if self.is_ignored(new_path) || old == new {
+            return;
+        }
+        match (old, new) {
+            (Value::Object(old), Value::Object(new)) => self.diff_objects(old, new, old_path, new_path, result),
+            (Value::Array(old), Value::Array(new)) => self.diff_arrays(old, new, old_path, new_path, result),
+            _ => {
+                result.patch.push(json!({ "op": "replace", "path": old_path.to_string(), "value": new }));
+                result.changes.push(Change::Changed {
+                    path: new_path.clone(),
+                    from: old.clone(),
+                    to: new.clone(),
+                });
+            }
+        }
+    }
+
+    fn diff_objects(
+        &self,
+        old: &Map<String, Value>,
+        new: &Map<String, Value>,
+        old_path: &Pointer,
+        new_path: &Pointer,
+        result: &mut DiffResult,
+    ) {
+        for (key, old_value) in old {
+            let (old_child, new_child) = (old_path.child(key), new_path.child(key));
+            match new.get(key) {
+                Some(new_value) => self.diff(old_value, new_value, &old_child, &new_child, result),
+                None if self.is_ignored(&new_child) => {}
+                None => {
+                    result.patch.push(json!({ "op": "remove", "path": old_child.to_string() }));
+                    result.changes.push(Change::Removed {
+                        path: new_child,
+                        value: old_value.clone(),
+                    });
+                }
+            }
+        }
+        for (key, new_value) in new.iter().filter(|(key, _)| !old.contains_key(*key)) {
+            let new_child = new_path.child(key);
+            if self.is_ignored(&new_child) {
+                continue;
+            }
+            result.patch.push(json!({ "op": "add", "path": old_path.child(key).to_string(), "value": new_value }));
+            result.changes.push(Change::Added {
+                path: new_child,
+                value: new_value.clone(),
+            });
+        }
+    }
+
+    fn diff_arrays(&self, old: &[Value], new: &[Value], old_path: &Pointer, new_path: &Pointer, result: &mut DiffResult) {
+        // Which old element ends up at each index of the new array, None for new ones.
+        // Elements on the longest common subsequence simply stayed where they were
+        let matched = longest_common_subsequence(old, new);
+        let mut source: Vec<Option<usize>> = vec![None; new.len()];
+        for &(i, j) in &matched {
+            source[j] = Some(i);
+        }
+        let mut unmatched: Vec<usize> = (0..old.len())
+            .filter(|i| !matched.iter().any(|(matched, _)| matched == i))
+            .collect();
+
+        // An element that was removed in one place and added in another was moved
+        for j in 0..new.len() {
+            if source[j].is_some() {
+                continue;
+            }
+            if let Some(position) = unmatched.iter().position(|&i| old[i] == new[j]) {
+                let i = unmatched.remove(position);
+                source[j] = Some(i);
+                if !self.is_ignored(&new_path.child(j)) {
+                    result.changes.push(Change::Moved {
+                        from: old_path.child(i),
+                        to: new_path.child(j),
+                    });
+                }
+            }
+        }
+
+        // Between two elements that stayed, old elements were replaced by new ones.
+        // Taking the place of an old element counts as changing it
+        let mut previous = (0, 0);
+        for (next_i, next_j) in matched.iter().copied().chain(Some((old.len(), new.len()))) {
+            let replaced: Vec<usize> = unmatched
+                .iter()
+                .copied()
+                .filter(|i| (previous.0..next_i).contains(i))
+                .collect();
+            let replacements: Vec<usize> = (previous.1..next_j).filter(|&j| source[j].is_none()).collect();
+            for (i, j) in replaced.into_iter().zip(replacements) {
+                source[j] = Some(i);
+                unmatched.retain(|&unmatched| unmatched != i);
+                self.diff(&old[i], &new[j], &old_path.child(i), &new_path.child(j), result);
+            }
+            previous = (next_i + 1, next_j + 1);
+        }
+
+        // Ignored elements are neither reported nor touched by the patch,
+        // so an ignored removed element stays right after the one before it
+        let (kept, removed): (Vec<usize>, Vec<usize>) =
+            unmatched.iter().partition(|&&i| self.is_ignored(&old_path.child(i)));
+        for &i in &removed {
+            result.changes.push(Change::Removed {
+                path: old_path.child(i),
+                value: old[i].clone(),
+            });
+        }
+        let mut target = Vec::new();
+        for (j, source) in source.iter().enumerate() {
+            match *source {
+                Some(i) => target.push(Slot::Old(i)),
+                None if self.is_ignored(&new_path.child(j)) => {}
+                None => {
+                    target.push(Slot::New(j));
+                    result.changes.push(Change::Added {
+                        path: new_path.child(j),
+                        value: new[j].clone(),
+                    });
+                }
+            }
+        }
+        for &i in &kept {
+            let position = target
+                .iter()
+                .rposition(|slot| matches!(*slot, Slot::Old(before) if before < i))
+                .map_or(0, |position| position + 1);
+            target.insert(position, Slot::Old(i));
+        }
+        self.reorder(old_path, old.len(), &removed, &target, new, result);
+    }
+
+    // Turn the current array into the target order with as few operations as we can.
+    // Each operation sees the array as the previous one left it, so we keep
+    // track of which old element is at which position right now
+    fn reorder(
+        &self,
+        path: &Pointer,
+        old_len: usize,
+        removed: &[usize],
+        target: &[Slot],
+        new: &[Value],
+        result: &mut DiffResult,
+    ) {
+        // Removing from the back keeps the indices of what's left in front valid
+        let mut current: Vec<Slot> = (0..old_len).filter(|i| !removed.contains(i)).map(Slot::Old).collect();
+        for &i in removed.iter().rev() {
+            result.patch.push(json!({ "op": "remove", "path": path.child(i).to_string() }));
+        }
+        for (position, slot) in target.iter().enumerate() {
+            match slot {
+                Slot::New(j) => {
+                    result.patch.push(json!({ "op": "add", "path": path.child(position).to_string(), "value": new[*j] }));
+                    current.insert(position, *slot);
+                }
+                Slot::Old(_) => {
+                    let from = current.iter().position(|current| current == slot).expect("Old elements are never lost");
+                    // Moving an ignored element would be a change nobody asked for
+                    let ignored = self.is_ignored(&path.child(from)) || self.is_ignored(&path.child(position));
+                    if from != position && !ignored {
+                        result.patch.push(json!({
+                            "op": "move",
+                            "from": path.child(from).to_string(),
+                            "path": path.child(position).to_string()
+                        }));
+                        let moved = current.remove(from);
+                        current.insert(position, moved);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Where an element of the new array comes from
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Slot {
+    Old(usize),
+    New(usize),
+}
+
+// The pairs of indices of equal elements that keep their order in both arrays
+fn longest_common_subsequence(old: &[Value], new: &[Value]) -> Vec<(usize, usize)> {
+    // lengths[i][j] is the length of the LCS of old[i..] and new[j..]
+    let mut lengths = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
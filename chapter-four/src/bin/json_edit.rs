@@ -0,0 +1,614 @@
+use serde_json::{json, Map, Value};
+use std::cmp::Ordering;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::{env, fmt, process};
+
+fn main() {
+    // Starts with pet_owner.json from json.rs, or whatever file is given
+    let path = env::args().nth(1).unwrap_or_else(|| "pet_owner.json".to_string());
+    let mut document = match fs::read_to_string(&path) {
+        Ok(content) => match serde_json::from_str(&content) {
+            Ok(document) => document,
+            Err(e) => {
+                eprintln!("{} is not valid JSON: {}", path, e);
+                process::exit(1);
+            }
+        },
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => json!({}),
+        Err(e) => {
+            eprintln!("Failed to read {}: {}", path, e);
+            process::exit(1);
+        }
+    };
+
+    println!("Editing {}, enter 'help' to see what you can do", path);
+    let stdin = io::stdin();
+    prompt();
+    for input in stdin.lock().lines() {
+        let input = input.expect("Failed to read line");
+        let input = input.trim();
+        let (command, argument) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+        let argument = argument.trim();
+        let result = match command {
+            "" => Ok(()),
+            "help" => {
+                println!("{}", HELP);
+                Ok(())
+            }
+            "show" => {
+                println!("{}", serde_json::to_string_pretty(&document).expect("Values are always valid JSON"));
+                Ok(())
+            }
+            "get" => get(&document, argument),
+            "query" => query(&document, argument),
+            "set" => set(&mut document, argument),
+            "patch" => parse_json(argument).and_then(|patch| apply_patch(&mut document, &patch)),
+            "merge" => parse_json(argument).map(|patch| merge_patch(&mut document, &patch)),
+            "save" => {
+                let target = if argument.is_empty() { path.as_str() } else { argument };
+                save(&document, target)
+            }
+            _ => Err(EditError::Usage(format!("Unknown command '{}', try 'help'", command))),
+        };
+        if let Err(e) = result {
+            println!("Error: {}", e);
+        }
+        prompt();
+    }
+}
+
+const HELP: &str = "\
+show                    print the whole document
+get <pointer>           print the value at a JSON Pointer, like /pets/0/name
+query <path>            print everything a JSONPath matches, like $.pets[?(@.age > 10)].name
+set <key.path> <value>  set a value, like 'set pets.0.age 3'. Values are parsed as JSON if possible
+patch <patch>           apply a JSON Patch, like [{\"op\": \"remove\", \"path\": \"/age\"}]
+merge <patch>           apply a JSON Merge Patch, like {\"age\": 24, \"phones\": null}
+save [file]             write the document back to disk";
+
+fn prompt() {
+    print!("> ");
+    io::stdout().flush().expect("Failed to flush stdout");
+}
+
+#[derive(Debug)]
+enum EditError {
+    Json(serde_json::Error),
+    Io(io::Error),
+    InvalidPointer(String),
+    InvalidPath(String),
+    // The document doesn't have what the command needs
+    NotFound(String),
+    // A JSON Patch operation is malformed or its test failed
+    Patch { index: usize, message: String },
+    Usage(String),
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            EditError::Json(ref err) => write!(f, "Invalid JSON: {}", err),
+            EditError::Io(ref err) => write!(f, "IO error: {}", err),
+            EditError::InvalidPointer(ref pointer) => write!(f, "'{}' is not a valid JSON Pointer", pointer),
+            EditError::InvalidPath(ref message) => write!(f, "Invalid JSONPath: {}", message),
+            EditError::NotFound(ref what) => write!(f, "{} doesn't exist", what),
+            EditError::Patch { index, ref message } => {
+                write!(f, "Operation {} of the patch failed, nothing was changed: {}", index, message)
+            }
+            EditError::Usage(ref message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<serde_json::Error> for EditError {
+    fn from(err: serde_json::Error) -> EditError {
+        EditError::Json(err)
+    }
+}
+
+impl From<io::Error> for EditError {
+    fn from(err: io::Error) -> EditError {
+        EditError::Io(err)
+    }
+}
+
+fn parse_json(input: &str) -> Result<Value, EditError> {
+    Ok(serde_json::from_str(input)?)
+}
+
+fn get(document: &Value, pointer: &str) -> Result<(), EditError> {
+    let tokens = parse_pointer(pointer)?;
+    let value = resolve(document, &tokens).ok_or_else(|| EditError::NotFound(format!("'{}'", pointer)))?;
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+fn query(document: &Value, path: &str) -> Result<(), EditError> {
+    let matches = JsonPath::parse(path)?.select(document);
+    if matches.is_empty() {
+        println!("Nothing matched");
+    }
+    for (pointer, value) in matches {
+        println!("{}: {}", if pointer.is_empty() { "/" } else { &pointer }, value);
+    }
+    Ok(())
+}
+
+// The shorthand from dynamic_json: a dotted key path and a value
+fn set(document: &mut Value, argument: &str) -> Result<(), EditError> {
+    let (path, value) = argument
+        .split_once(char::is_whitespace)
+        .ok_or_else(|| EditError::Usage("Please enter both a key and a value, like 'set age 24'".to_string()))?;
+    let value = serde_json::from_str(value.trim()).unwrap_or_else(|_| json!(value.trim()));
+    // Just like a patch, a failed insert leaves the document as it was
+    let mut edited = document.clone();
+    insert(&mut edited, path, value).map_err(EditError::Usage)?;
+    *document = edited;
+    Ok(())
+}
+
+// Same as insert in dynamic_json
+fn insert(document: &mut Value, path: &str, value: Value) -> Result<(), String> {
+    let mut current = document;
+    for key in path.split('.') {
+        if key.is_empty() {
+            return Err(format!("'{}' contains an empty key", path));
+        }
+        current = match current {
+            Value::Array(array) => {
+                let index: usize = key
+                    .parse()
+                    .map_err(|_| format!("'{}' is not a valid array index", key))?;
+                if index == array.len() {
+                    array.push(Value::Null);
+                }
+                array
+                    .get_mut(index)
+                    .ok_or_else(|| format!("Index {} is out of bounds", index))?
+            }
+            // Null is what we just pushed, or a value that was never set
+            Value::Null => {
+                *current = json!({});
+                current
+                    .as_object_mut()
+                    .expect("We just made it an object")
+                    .entry(key)
+                    .or_insert(Value::Null)
+            }
+            Value::Object(object) => object.entry(key).or_insert(Value::Null),
+            other => return Err(format!("Can't add '{}' to {}, it's not an object", key, other)),
+        };
+    }
+    *current = value;
+    Ok(())
+}
+
+fn save(document: &Value, path: &str) -> Result<(), EditError> {
+    // Write next to the real file and rename, so a crash
+    // never leaves a half-written document behind
+    let temporary = format!("{}.tmp", path);
+    fs::write(&temporary, serde_json::to_string_pretty(document)? + "\n")?;
+    fs::rename(temporary, path)?;
+    println!("Saved to {}", path);
+    Ok(())
+}
+
+// JSON Pointer, RFC 6901. "/pets/0/name" becomes ["pets", "0", "name"],
+// with ~1 standing for a slash and ~0 for a tilde inside a key
+fn parse_pointer(pointer: &str) -> Result<Vec<String>, EditError> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    if !pointer.starts_with('/') {
+        return Err(EditError::InvalidPointer(pointer.to_string()));
+    }
+    pointer[1..]
+        .split('/')
+        .map(|token| {
+            // A tilde not followed by 0 or 1 is an error
+            let mut rest = token;
+            while let Some(position) = rest.find('~') {
+                if !matches!(rest.as_bytes().get(position + 1), Some(b'0') | Some(b'1')) {
+                    return Err(EditError::InvalidPointer(pointer.to_string()));
+                }
+                rest = &rest[position + 2..];
+            }
+            Ok(token.replace("~1", "/").replace("~0", "~"))
+        })
+        .collect()
+}
+
+fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+// Array indices are plain decimal numbers without leading zeros
+fn array_index(token: &str) -> Option<usize> {
+    if token.len() > 1 && token.starts_with('0') {
+        return None;
+    }
+    token.parse().ok()
+}
+
+fn resolve<'a>(document: &'a Value, tokens: &[String]) -> Option<&'a Value> {
+    tokens.iter().try_fold(document, |value, token| match value {
+        Value::Object(object) => object.get(token),
+        Value::Array(array) => array.get(array_index(token)?),
+        _ => None,
+    })
+}
+
+fn resolve_mut<'a>(document: &'a mut Value, tokens: &[String]) -> Option<&'a mut Value> {
+    tokens.iter().try_fold(document, |value, token| match value {
+        Value::Object(object) => object.get_mut(token),
+        Value::Array(array) => array.get_mut(array_index(token)?),
+        _ => None,
+    })
+}
+
+// JSON Patch, RFC 6902. Either every operation succeeds, or the document stays untouched
+fn apply_patch(document: &mut Value, patch: &Value) -> Result<(), EditError> {
+    let operations = patch
+        .as_array()
+        .ok_or_else(|| EditError::Usage("A JSON Patch has to be an array of operations".to_string()))?;
+    // Work on a copy, so a failing operation can't leave half a patch behind
+    let mut patched = document.clone();
+    for (index, operation) in operations.iter().enumerate() {
+        apply_operation(&mut patched, operation).map_err(|message| EditError::Patch { index, message })?;
+    }
+    *document = patched;
+    Ok(())
+}
+
+fn apply_operation(document: &mut Value, operation: &Value) -> Result<(), String> {
+    let member = |name: &str| {
+        operation
+            .get(name)
+            .ok_or_else(|| format!("'{}' is missing", name))
+    };
+    let pointer = |name: &str| -> Result<Vec<String>, String> {
+        let pointer = member(name)?
+            .as_str()
+            .ok_or_else(|| format!("'{}' has to be a string", name))?;
+        parse_pointer(pointer).map_err(|e| e.to_string())
+    };
+    let op = member("op")?.as_str().ok_or("'op' has to be a string")?;
+    match op {
+        "add" => add(document, &pointer("path")?, member("value")?.clone()),
+        "remove" => remove(document, &pointer("path")?).map(|_| ()),
+        "replace" => {
+            let path = pointer("path")?;
+            let target = resolve_mut(document, &path).ok_or_else(|| not_found(&path))?;
+            *target = member("value")?.clone();
+            Ok(())
+        }
+        "move" => {
+            let (from, path) = (pointer("from")?, pointer("path")?);
+            // Moving a value into itself would make it disappear
+            if path.len() > from.len() && path.starts_with(&from) {
+                return Err("can't move a value into one of its own children".to_string());
+            }
+            let value = remove(document, &from)?;
+            add(document, &path, value)
+        }
+        "copy" => {
+            let (from, path) = (pointer("from")?, pointer("path")?);
+            let value = resolve(document, &from).ok_or_else(|| not_found(&from))?.clone();
+            add(document, &path, value)
+        }
+        "test" => {
+            let path = pointer("path")?;
+            let actual = resolve(document, &path).ok_or_else(|| not_found(&path))?;
+            let expected = member("value")?;
+            if same_value(actual, expected) {
+                Ok(())
+            } else {
+                Err(format!("expected {} at {}, found {}", expected, join(&path), actual))
+            }
+        }
+        other => Err(format!("unknown operation '{}'", other)),
+    }
+}
+
+// Like ==, except that numbers are compared by value, so 1 and 1.0 are equal
+fn same_value(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => match (a.as_i64(), b.as_i64(), a.as_u64(), b.as_u64()) {
+            (Some(a), Some(b), _, _) => a == b,
+            (_, _, Some(a), Some(b)) => a == b,
+            _ => a.as_f64() == b.as_f64(),
+        },
+        (Value::Array(a), Value::Array(b)) => a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same_value(a, b)),
+        (Value::Object(a), Value::Object(b)) => {
+            a.len() == b.len() && a.iter().all(|(key, a)| b.get(key).is_some_and(|b| same_value(a, b)))
+        }
+        (a, b) => a == b,
+    }
+}
+
+fn join(tokens: &[String]) -> String {
+    tokens.iter().map(|token| format!("/{}", escape(token))).collect()
+}
+
+fn not_found(tokens: &[String]) -> String {
+    format!("{} doesn't exist", join(tokens))
+}
+
+fn add(document: &mut Value, path: &[String], value: Value) -> Result<(), String> {
+    let (last, parent) = match path.split_last() {
+        Some(split) => split,
+        // An empty path replaces the whole document
+        None => {
+            *document = value;
+            return Ok(());
+        }
+    };
+    match resolve_mut(document, parent).ok_or_else(|| not_found(parent))? {
+        Value::Object(object) => {
+            object.insert(last.clone(), value);
+        }
+        Value::Array(array) => {
+            // "-" means after the last element
+            let index = if last == "-" {
+                array.len()
+            } else {
+                array_index(last).ok_or_else(|| format!("'{}' is not an array index", last))?
+            };
+            if index > array.len() {
+                return Err(format!("index {} is out of bounds at {}", index, join(parent)));
+            }
+            array.insert(index, value);
+        }
+        _ => return Err(format!("{} is neither an object nor an array", join(parent))),
+    }
+    Ok(())
+}
+
+fn remove(document: &mut Value, path: &[String]) -> Result<Value, String> {
+    let (last, parent) = path.split_last().ok_or("can't remove the whole document")?;
+    let removed = match resolve_mut(document, parent).ok_or_else(|| not_found(parent))? {
+        Value::Object(object) => object.remove(last),
+        Value::Array(array) => match array_index(last) {
+            Some(index) if index < array.len() => Some(array.remove(index)),
+            _ => None,
+        },
+        _ => None,
+    };
+    removed.ok_or_else(|| not_found(path))
+}
+
+// JSON Merge Patch, RFC 7396. The patch looks like the document,
+// null removes a key and everything else is merged in recursively
+fn merge_patch(document: &mut Value, patch: &Value) {
+    let patch = match patch {
+        Value::Object(patch) => patch,
+        // Anything but an object simply replaces what was there
+        other => {
+            *document = other.clone();
+            return;
+        }
+    };
+    if !document.is_object() {
+        *document = Value::Object(Map::new());
+    }
+    let object = document.as_object_mut().expect("We just made sure it's an object");
+    for (key, value) in patch {
+        if value.is_null() {
+            object.remove(key);
+        } else {
+            merge_patch(object.entry(key.as_str()).or_insert(Value::Null), value);
+        }
+    }
+}
+
+// The part of JSONPath we support. $ is the root, followed by
+// .key or ['key'], [0] or [-1], .* or [*], ..key for any depth,
+// and filters like [?(@.age > 10)] or [?(@.colour)]
+#[derive(Debug)]
+enum Segment {
+    Key(String),
+    Index(i64),
+    Wildcard,
+    // The current value and everything below it, for ..
+    Descendants,
+    Filter(Filter),
+}
+
+#[derive(Debug)]
+struct Filter {
+    // The keys after @, like ["owner", "age"] for @.owner.age
+    path: Vec<String>,
+    // Without a comparison, the filter only checks that the key exists
+    comparison: Option<(String, Value)>,
+}
+
+impl Filter {
+    fn matches(&self, value: &Value) -> bool {
+        let actual = match self.path.iter().try_fold(value, |value, key| value.get(key)) {
+            Some(actual) => actual,
+            None => return false,
+        };
+        let (operator, expected) = match &self.comparison {
+            Some(comparison) => comparison,
+            None => return true,
+        };
+        let ordering = match (actual, expected) {
+            (Value::Number(a), Value::Number(b)) => a.as_f64().partial_cmp(&b.as_f64()),
+            (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+            (a, b) if a == b => Some(Ordering::Equal),
+            _ => None,
+        };
+        match (operator.as_str(), ordering) {
+            ("==", Some(ordering)) => ordering == Ordering::Equal,
+            ("!=", ordering) => ordering != Some(Ordering::Equal),
+            ("<", Some(ordering)) => ordering == Ordering::Less,
+            ("<=", Some(ordering)) => ordering != Ordering::Greater,
+            (">", Some(ordering)) => ordering == Ordering::Greater,
+            (">=", Some(ordering)) => ordering != Ordering::Less,
+            _ => false,
+        }
+    }
+}
+
+struct JsonPath {
+    segments: Vec<Segment>,
+}
+
+impl JsonPath {
+    fn parse(path: &str) -> Result<JsonPath, EditError> {
+        let error = |message: &str| EditError::InvalidPath(format!("{} in '{}'", message, path));
+        let mut rest = path.trim().strip_prefix('$').ok_or_else(|| error("it has to start with $"))?;
+        let mut segments = Vec::new();
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix("..") {
+                segments.push(Segment::Descendants);
+                // ..key is short for ..[key], but ..[0] works too
+                rest = if after.starts_with('[') { after } else { &rest[1..] };
+            } else if let Some(after) = rest.strip_prefix('.') {
+                let end = after.find(['.', '[']).unwrap_or(after.len());
+                let key = &after[..end];
+                segments.push(match key {
+                    "" => return Err(error("expected a key after '.'")),
+                    "*" => Segment::Wildcard,
+                    key => Segment::Key(key.to_string()),
+                });
+                rest = &after[end..];
+            } else if let Some(after) = rest.strip_prefix('[') {
+                let end = find_closing_bracket(after).ok_or_else(|| error("missing ]"))?;
+                let inside = after[..end].trim();
+                segments.push(parse_bracket(inside).ok_or_else(|| error(&format!("can't understand [{}]", inside)))?);
+                rest = &after[end + 1..];
+            } else {
+                return Err(error(&format!("unexpected '{}'", rest)));
+            }
+        }
+        Ok(JsonPath { segments })
+    }
+
+    // Every match, together with the JSON Pointer of where it was found
+    fn select<'a>(&self, document: &'a Value) -> Vec<(String, &'a Value)> {
+        let mut current = vec![(String::new(), document)];
+        for segment in &self.segments {
+            let mut next = Vec::new();
+            for (pointer, value) in current {
+                match segment {
+                    Segment::Key(key) => {
+                        if let Some(child) = value.get(key) {
+                            next.push((format!("{}/{}", pointer, escape(key)), child));
+                        }
+                    }
+                    Segment::Index(index) => {
+                        if let Some(array) = value.as_array() {
+                            // Negative indices count from the end
+                            let index = if *index < 0 { array.len() as i64 + index } else { *index };
+                            if let Some(child) = usize::try_from(index).ok().and_then(|index| array.get(index)) {
+                                next.push((format!("{}/{}", pointer, index), child));
+                            }
+                        }
+                    }
+                    Segment::Wildcard => next.extend(children(&pointer, value)),
+                    Segment::Descendants => descendants(pointer, value, &mut next),
+                    Segment::Filter(filter) => {
+                        next.extend(children(&pointer, value).filter(|(_, child)| filter.matches(child)))
+                    }
+                }
+            }
+            current = next;
+        }
+        current
+    }
+}
+
+fn children<'a>(pointer: &str, value: &'a Value) -> Box<dyn Iterator<Item = (String, &'a Value)> + 'a> {
+    let pointer = pointer.to_string();
+    match value {
+        Value::Object(object) => Box::new(
+            object
+                .iter()
+                .map(move |(key, child)| (format!("{}/{}", pointer, escape(key)), child)),
+        ),
+        Value::Array(array) => Box::new(
+            array
+                .iter()
+                .enumerate()
+                .map(move |(index, child)| (format!("{}/{}", pointer, index), child)),
+        ),
+        _ => Box::new(std::iter::empty()),
+    }
+}
+
+fn descendants<'a>(pointer: String, value: &'a Value, found: &mut Vec<(String, &'a Value)>) {
+    let children: Vec<_> = children(&pointer, value).collect();
+    found.push((pointer, value));
+    for (pointer, child) in children {
+        descendants(pointer, child, found);
+    }
+}
+
+// Quotes may contain a ], so we can't just look for the next one
+fn find_closing_bracket(input: &str) -> Option<usize> {
+    let mut quote = None;
+    let mut depth = 0;
+    for (position, c) in input.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'') | (None, '"') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => depth -= 1,
+            (None, ']') if depth == 0 => return Some(position),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_bracket(inside: &str) -> Option<Segment> {
+    if inside == "*" {
+        return Some(Segment::Wildcard);
+    }
+    if let Ok(index) = inside.parse() {
+        return Some(Segment::Index(index));
+    }
+    if let Some(key) = unquote(inside) {
+        return Some(Segment::Key(key));
+    }
+    let filter = inside.strip_prefix("?(")?.strip_suffix(')')?.trim();
+    let rest = filter.strip_prefix('@')?;
+    // The path after @ ends where the comparison begins
+    let end = rest.find(|c: char| c.is_whitespace() || "=!<>".contains(c)).unwrap_or(rest.len());
+    let path = rest[..end]
+        .split('.')
+        .skip(1)
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    if !rest[..end].is_empty() && (!rest.starts_with('.') || path.iter().any(String::is_empty)) {
+        return None;
+    }
+    let condition = rest[end..].trim();
+    if condition.is_empty() {
+        return Some(Segment::Filter(Filter { path, comparison: None }));
+    }
+    let operator = ["==", "!=", "<=", ">=", "<", ">"]
+        .iter()
+        .find(|operator| condition.starts_with(*operator))?;
+    let literal = condition[operator.len()..].trim();
+    // 'single quotes' are common in JSONPath, but not valid JSON
+    let expected = unquote(literal)
+        .map(Value::String)
+        .or_else(|| serde_json::from_str(literal).ok())?;
+    Some(Segment::Filter(Filter {
+        path,
+        comparison: Some((operator.to_string(), expected)),
+    }))
+}
+
+fn unquote(input: &str) -> Option<String> {
+    let quoted = |quote: char| input.len() >= 2 && input.starts_with(quote) && input.ends_with(quote);
+    if quoted('\'') || quoted('"') {
+        Some(input[1..input.len() - 1].to_string())
+    } else {
+        None
+    }
+}
@@ -0,0 +1,398 @@
+use schemars::{schema_for, JsonSchema};
+use serde_derive::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+
+// The same types as in json.rs. Deriving JsonSchema next to
+// Serialize and Deserialize keeps the schema in sync with the code
+#[derive(Serialize, Deserialize, JsonSchema)]
+struct PetOwner {
+    name: String,
+    age: u8,
+    pets: Vec<Pet>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+struct Pet {
+    name: String,
+    species: AllowedSpecies,
+    // It is usual for many JSON keys to be optional
+    age: Option<u8>,
+    colour: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+enum AllowedSpecies {
+    Dog,
+    Turtle,
+    Cat,
+}
+
+fn main() {
+    // This is the contract we can publish for our partners
+    let schema = serde_json::to_value(schema_for!(PetOwner)).expect("Failed to convert schema into JSON");
+    println!("JSON Schema for PetOwner:");
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&schema).expect("Failed to format schema")
+    );
+
+    // The file written by the json recipe follows the contract
+    let file = File::open("pet_owner.json").expect("Failed to open pet_owner.json");
+    let document: Value = serde_json::from_reader(BufReader::new(file)).expect("Failed to parse JSON");
+    report("pet_owner.json", &validate(&schema, &document));
+
+    // serde would stop at the first problem in here.
+    // The validator tells us about all of them at once
+    let broken = json!({
+        "name": 42,
+        "age": 300,
+        "pets": [
+            { "name": "Waldo", "species": "Dog", "age": 2 },
+            { "name": "Nemo", "species": "Fish", "colour": ["orange", "white"] },
+            { "species": "Cat", "age": -1 }
+        ]
+    });
+    report("the broken document", &validate(&schema, &broken));
+}
+
+fn report(name: &str, violations: &[Violation]) {
+    if violations.is_empty() {
+        println!("\n{} is valid", name);
+        return;
+    }
+    println!("\n{} has {} problems:", name, violations.len());
+    for violation in violations {
+        println!("  {}", violation);
+    }
+}
+
+// One way in which a document breaks the schema
+#[derive(Debug, Clone, PartialEq)]
+struct Violation {
+    // JSON Pointer to the offending value, "" being the whole document
+    path: String,
+    keyword: &'static str,
+    message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let path = if self.path.is_empty() { "/" } else { &self.path };
+        write!(f, "{} ({}): {}", path, self.keyword, self.message)
+    }
+}
+
+// Check a document against a schema and collect every violation.
+// Supports the parts of draft-07 that schemars generates and
+// the validation keywords people add by hand most often
+fn validate(schema: &Value, instance: &Value) -> Vec<Violation> {
+    let mut validator = Validator {
+        root: schema,
+        violations: Vec::new(),
+    };
+    validator.check(schema, instance, "");
+    validator.violations
+}
+
+struct Validator<'a> {
+    // Needed to resolve references like "#/definitions/Pet"
+    root: &'a Value,
+    violations: Vec<Violation>,
+}
+
+impl<'a> Validator<'a> {
+    fn fail(&mut self, path: &str, keyword: &'static str, message: String) {
+        self.violations.push(Violation {
+            path: path.to_string(),
+            keyword,
+            message,
+        });
+    }
+
+    // Run a check on the side and only return its violations
+    fn probe(&self, schema: &Value, instance: &Value, path: &str) -> Vec<Violation> {
+        let mut validator = Validator {
+            root: self.root,
+            violations: Vec::new(),
+        };
+        validator.check(schema, instance, path);
+        validator.violations
+    }
+
+    fn check(&mut self, schema: &Value, instance: &Value, path: &str) {
+        let schema = match schema {
+            // true accepts everything, false nothing
+            Value::Bool(true) => return,
+            Value::Bool(false) => {
+                return self.fail(path, "false", "No value is allowed here".to_string());
+            }
+            Value::Object(schema) => schema,
+            _ => return,
+        };
+
+        if let Some(Value::String(reference)) = schema.get("$ref") {
+            match self.resolve(reference) {
+                Some(target) => self.check(target, instance, path),
+                None => self.fail(path, "$ref", format!("Can't resolve reference {}", reference)),
+            }
+            // In draft-07, $ref replaces all other keywords next to it
+            return;
+        }
+
+        self.check_type(schema, instance, path);
+        self.check_enum(schema, instance, path);
+        self.check_combinators(schema, instance, path);
+        match instance {
+            Value::Object(object) => self.check_object(schema, object, path),
+            Value::Array(array) => self.check_array(schema, array, path),
+            Value::String(string) => self.check_string(schema, string, path),
+            Value::Number(_) => self.check_number(schema, instance, path),
+            _ => {}
+        }
+    }
+
+    fn resolve(&self, reference: &str) -> Option<&'a Value> {
+        // Only references into the same document are supported
+        let pointer = reference.strip_prefix('#')?;
+        self.root.pointer(pointer)
+    }
+
+    fn check_type(&mut self, schema: &Map<String, Value>, instance: &Value, path: &str) {
+        let allowed: Vec<&str> = match schema.get("type") {
+            Some(Value::String(name)) => vec![name.as_str()],
+            Some(Value::Array(names)) => names.iter().filter_map(Value::as_str).collect(),
+            _ => return,
+        };
+        if !allowed.iter().any(|&name| has_type(instance, name)) {
+            self.fail(
+                path,
+                "type",
+                format!("Expected {}, found {}", allowed.join(" or "), type_name(instance)),
+            );
+        }
+    }
+
+    fn check_enum(&mut self, schema: &Map<String, Value>, instance: &Value, path: &str) {
+        if let Some(Value::Array(options)) = schema.get("enum") {
+            if !options.contains(instance) {
+                let options: Vec<String> = options.iter().map(Value::to_string).collect();
+                self.fail(
+                    path,
+                    "enum",
+                    format!("{} is not one of {}", instance, options.join(", ")),
+                );
+            }
+        }
+        if let Some(expected) = schema.get("const") {
+            if expected != instance {
+                self.fail(path, "const", format!("Expected {}, found {}", expected, instance));
+            }
+        }
+    }
+
+    fn check_combinators(&mut self, schema: &Map<String, Value>, instance: &Value, path: &str) {
+        if let Some(Value::Array(schemas)) = schema.get("allOf") {
+            for sub_schema in schemas {
+                self.check(sub_schema, instance, path);
+            }
+        }
+        if let Some(Value::Array(schemas)) = schema.get("anyOf") {
+            let any_matches = schemas
+                .iter()
+                .any(|sub_schema| self.probe(sub_schema, instance, path).is_empty());
+            if !any_matches {
+                self.fail(path, "anyOf", "Value matches none of the allowed schemas".to_string());
+            }
+        }
+        if let Some(Value::Array(schemas)) = schema.get("oneOf") {
+            let matches = schemas
+                .iter()
+                .filter(|sub_schema| self.probe(sub_schema, instance, path).is_empty())
+                .count();
+            if matches != 1 {
+                self.fail(
+                    path,
+                    "oneOf",
+                    format!("Value must match exactly one schema, but matches {}", matches),
+                );
+            }
+        }
+        if let Some(sub_schema) = schema.get("not") {
+            if self.probe(sub_schema, instance, path).is_empty() {
+                self.fail(path, "not", "Value matches a schema it must not match".to_string());
+            }
+        }
+    }
+
+    fn check_object(&mut self, schema: &Map<String, Value>, object: &Map<String, Value>, path: &str) {
+        if let Some(Value::Array(required)) = schema.get("required") {
+            for key in required.iter().filter_map(Value::as_str) {
+                if !object.contains_key(key) {
+                    self.fail(path, "required", format!("Missing required property '{}'", key));
+                }
+            }
+        }
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for (key, value) in object {
+            let child_path = format!("{}/{}", path, escape_pointer(key));
+            match properties.and_then(|properties| properties.get(key)) {
+                Some(property_schema) => self.check(property_schema, value, &child_path),
+                None => match schema.get("additionalProperties") {
+                    Some(Value::Bool(false)) => {
+                        self.fail(&child_path, "additionalProperties", format!("Unknown property '{}'", key))
+                    }
+                    Some(additional) => self.check(additional, value, &child_path),
+                    None => {}
+                },
+            }
+        }
+        self.check_count(schema, "minProperties", "maxProperties", object.len(), "properties", path);
+    }
+
+    fn check_array(&mut self, schema: &Map<String, Value>, array: &[Value], path: &str) {
+        match schema.get("items") {
+            // A single schema applies to every item
+            Some(items @ Value::Object(_)) | Some(items @ Value::Bool(_)) => {
+                for (index, item) in array.iter().enumerate() {
+                    self.check(items, item, &format!("{}/{}", path, index));
+                }
+            }
+            // An array of schemas describes a tuple
+            Some(Value::Array(items)) => {
+                for (index, (item_schema, item)) in items.iter().zip(array).enumerate() {
+                    self.check(item_schema, item, &format!("{}/{}", path, index));
+                }
+                if let Some(additional) = schema.get("additionalItems") {
+                    for (index, item) in array.iter().enumerate().skip(items.len()) {
+                        self.check(additional, item, &format!("{}/{}", path, index));
+                    }
+                }
+            }
+            _ => {}
+        }
+        self.check_count(schema, "minItems", "maxItems", array.len(), "items", path);
+        if schema.get("uniqueItems") == Some(&Value::Bool(true)) {
+            for (index, item) in array.iter().enumerate() {
+                if array[..index].contains(item) {
+                    self.fail(
+                        &format!("{}/{}", path, index),
+                        "uniqueItems",
+                        format!("{} appears more than once", item),
+                    );
+                }
+            }
+        }
+    }
+
+    fn check_string(&mut self, schema: &Map<String, Value>, string: &str, path: &str) {
+        // Lengths are counted in characters, not bytes
+        let len = string.chars().count();
+        self.check_count(schema, "minLength", "maxLength", len, "characters", path);
+    }
+
+    fn check_count(
+        &mut self,
+        schema: &Map<String, Value>,
+        min_keyword: &'static str,
+        max_keyword: &'static str,
+        count: usize,
+        what: &str,
+        path: &str,
+    ) {
+        if let Some(min) = schema.get(min_keyword).and_then(Value::as_u64) {
+            if (count as u64) < min {
+                self.fail(path, min_keyword, format!("Expected at least {} {}, found {}", min, what, count));
+            }
+        }
+        if let Some(max) = schema.get(max_keyword).and_then(Value::as_u64) {
+            if count as u64 > max {
+                self.fail(path, max_keyword, format!("Expected at most {} {}, found {}", max, what, count));
+            }
+        }
+    }
+
+    fn check_number(&mut self, schema: &Map<String, Value>, instance: &Value, path: &str) {
+        let number = match instance.as_f64() {
+            Some(number) => number,
+            None => return,
+        };
+        let bound = |keyword| schema.get(keyword).and_then(Value::as_f64);
+        if let Some(minimum) = bound("minimum") {
+            if number < minimum {
+                self.fail(path, "minimum", format!("{} is less than {}", instance, minimum));
+            }
+        }
+        if let Some(maximum) = bound("maximum") {
+            if number > maximum {
+                self.fail(path, "maximum", format!("{} is greater than {}", instance, maximum));
+            }
+        }
+        if let Some(minimum) = bound("exclusiveMinimum") {
+            if number <= minimum {
+                self.fail(path, "exclusiveMinimum", format!("{} is not greater than {}", instance, minimum));
+            }
+        }
+        if let Some(maximum) = bound("exclusiveMaximum") {
+            if number >= maximum {
+                self.fail(path, "exclusiveMaximum", format!("{} is not less than {}", instance, maximum));
+            }
+        }
+        if let Some(divisor) = bound("multipleOf") {
+            if divisor > 0.0 && (number / divisor).fract() != 0.0 {
+                self.fail(path, "multipleOf", format!("{} is not a multiple of {}", instance, divisor));
+            }
+        }
+        // schemars describes Rust's integer types with formats like "uint8"
+        if let Some((min, max)) = schema.get("format").and_then(Value::as_str).and_then(integer_range) {
+            if number < min || number > max {
+                self.fail(path, "format", format!("{} doesn't fit into the range {}..={}", instance, min, max));
+            }
+        }
+    }
+}
+
+fn integer_range(format: &str) -> Option<(f64, f64)> {
+    let range = match format {
+        "int8" => (i8::MIN as f64, i8::MAX as f64),
+        "int16" => (i16::MIN as f64, i16::MAX as f64),
+        "int32" => (i32::MIN as f64, i32::MAX as f64),
+        "uint8" => (0.0, u8::MAX as f64),
+        "uint16" => (0.0, u16::MAX as f64),
+        "uint32" => (0.0, u32::MAX as f64),
+        _ => return None,
+    };
+    Some(range)
+}
+
+fn has_type(instance: &Value, name: &str) -> bool {
+    match name {
+        "null" => instance.is_null(),
+        "boolean" => instance.is_boolean(),
+        "object" => instance.is_object(),
+        "array" => instance.is_array(),
+        "string" => instance.is_string(),
+        "number" => instance.is_number(),
+        // 1.0 counts as an integer in JSON Schema
+        "integer" => instance.is_i64() || instance.is_u64() || instance.as_f64().is_some_and(|n| n.fract() == 0.0),
+        _ => false,
+    }
+}
+
+fn type_name(instance: &Value) -> &'static str {
+    match instance {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+// Keys may contain the characters JSON Pointer uses itself
+fn escape_pointer(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
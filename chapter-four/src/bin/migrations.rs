@@ -0,0 +1,452 @@
+use serde_derive::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::{env, error, fmt, io, process, result};
+use toml_edit::{DocumentMut, InlineTable, Item, Key, Table, TableLike, Value};
+
+// The Preferences from toml.rs, as they look after all migrations
+#[derive(Debug, Serialize, Deserialize)]
+struct Preferences {
+    schema_version: u32,
+    person: Person,
+    locale: Locale,
+    privacy: Privacy,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Person {
+    name: String,
+    email: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Locale {
+    display: String,
+    autocorrect: Option<Vec<String>>,
+    units: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Privacy {
+    statistics: String,
+    show_name: bool,
+    show_email: bool,
+}
+
+// Every change to the layout of the file gets a migration. They run in order,
+// each one taking the file from the previous version to its own.
+// Never change a migration that was already released, add a new one instead.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "Name the privacy flags after what they do",
+        apply: |root, changes| {
+            rename(root, "privacy.public_name", "privacy.show_name", changes)?;
+            rename(root, "privacy.public_email", "privacy.show_email", changes)
+        },
+    },
+    Migration {
+        version: 2,
+        description: "Move [language] to [locale]",
+        apply: |root, changes| rename(root, "language", "locale", changes),
+    },
+    Migration {
+        version: 3,
+        description: "Store the statistics consent as a level instead of a flag",
+        apply: |root, changes| {
+            change_type(root, "privacy.share_anonymous_statistics", changes, |value| match value.as_bool() {
+                Some(true) => Ok(Value::from("anonymous")),
+                Some(false) => Ok(Value::from("none")),
+                None => Err(format!("expected true or false, found {}", show(value))),
+            })?;
+            rename(root, "privacy.share_anonymous_statistics", "privacy.statistics", changes)
+        },
+    },
+    Migration {
+        version: 4,
+        description: "Add the unit system",
+        apply: |root, changes| set_default(root, "locale.units", Value::from("metric"), changes),
+    },
+];
+
+fn current_version() -> u32 {
+    MIGRATIONS.last().map_or(0, |migration| migration.version)
+}
+
+fn main() {
+    let mut path = PathBuf::from("preferences.toml");
+    let mut dry_run = false;
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--dry-run" | "-n" => dry_run = true,
+            flag if flag.starts_with('-') => {
+                eprintln!("Unknown option '{}'\n", flag);
+                eprintln!(
+                    "Usage: migrations [file] [--dry-run]\n\
+                     \n\
+                     Upgrades preferences.toml, as written by toml.rs, to the current schema version.\n\
+                     The original is kept next to it as <file>.v<version>.bak"
+                );
+                process::exit(2);
+            }
+            file => path = PathBuf::from(file),
+        }
+    }
+
+    let result = if dry_run {
+        plan(&path).map(|report| {
+            print!("{}", report);
+            println!("Dry run, nothing was written");
+        })
+    } else {
+        load(&path).map(|preferences| println!("{:#?}", preferences))
+    };
+    if let Err(e) = result {
+        eprintln!("Failed to load {}: {}", path.display(), e);
+        process::exit(1);
+    }
+}
+
+// Our custom error, following the pattern from chapter six
+#[derive(Debug)]
+enum MigrationError {
+    Io(io::Error),
+    Parse(toml_edit::TomlError),
+    Deserialize(toml::de::Error),
+    // The file was written by a newer version of the program
+    TooNew { found: u32, supported: u32 },
+    // A migration ran into data it doesn't know how to handle
+    Failed { version: u32, message: String },
+}
+
+type Result<T> = result::Result<T, MigrationError>;
+
+impl error::Error for MigrationError {
+    fn cause(&self) -> Option<&dyn error::Error> {
+        match *self {
+            MigrationError::Io(ref err) => Some(err),
+            MigrationError::Parse(ref err) => Some(err),
+            MigrationError::Deserialize(ref err) => Some(err),
+            MigrationError::TooNew { .. } | MigrationError::Failed { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MigrationError::Io(ref err) => write!(f, "IO error: {}", err),
+            MigrationError::Parse(ref err) => write!(f, "Parse error: {}", err),
+            MigrationError::Deserialize(ref err) => write!(f, "Deserialize error: {}", err),
+            MigrationError::TooNew { found, supported } => write!(
+                f,
+                "The file has schema version {}, but we only understand up to version {}",
+                found, supported
+            ),
+            MigrationError::Failed { version, ref message } => {
+                write!(f, "Migration to version {} failed: {}", version, message)
+            }
+        }
+    }
+}
+
+impl From<io::Error> for MigrationError {
+    fn from(err: io::Error) -> MigrationError {
+        MigrationError::Io(err)
+    }
+}
+
+impl From<toml_edit::TomlError> for MigrationError {
+    fn from(err: toml_edit::TomlError) -> MigrationError {
+        MigrationError::Parse(err)
+    }
+}
+
+impl From<toml::de::Error> for MigrationError {
+    fn from(err: toml::de::Error) -> MigrationError {
+        MigrationError::Deserialize(err)
+    }
+}
+
+struct Migration {
+    version: u32,
+    description: &'static str,
+    // Works on the document itself, since the old layout doesn't fit any struct we
+    // still have, and so comments and key order survive. Every change is described
+    // in the Vec, for the report
+    apply: fn(&mut Table, &mut Vec<String>) -> result::Result<(), String>,
+}
+
+// What migrating a file did, or would do
+struct Report {
+    path: PathBuf,
+    from: u32,
+    steps: Vec<(&'static Migration, Vec<String>)>,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.steps.is_empty() {
+            return writeln!(f, "{} is up to date at version {}", self.path.display(), self.from);
+        }
+        writeln!(
+            f,
+            "{} is at version {}, migrating to version {}",
+            self.path.display(),
+            self.from,
+            current_version()
+        )?;
+        for (migration, changes) in &self.steps {
+            writeln!(f, "  {}: {}", migration.version, migration.description)?;
+            if changes.is_empty() {
+                writeln!(f, "     nothing to do")?;
+            }
+            for change in changes {
+                writeln!(f, "     {}", change)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Files from before we had versions count as version 0
+fn schema_version(root: &Table) -> Result<u32> {
+    let version = match root.get("schema_version") {
+        None => 0,
+        Some(item) => match item.as_integer().map(u32::try_from) {
+            Some(Ok(version)) => version,
+            _ => {
+                return Err(MigrationError::Failed {
+                    version: 0,
+                    message: format!("schema_version has to be a positive integer, not {}", show_item(item)),
+                })
+            }
+        },
+    };
+    if version > current_version() {
+        return Err(MigrationError::TooNew {
+            found: version,
+            supported: current_version(),
+        });
+    }
+    Ok(version)
+}
+
+// Runs every migration the document still needs, without touching the file
+fn migrate(path: &Path, root: &mut Table) -> Result<Report> {
+    // Skipping or repeating a version number would silently skip or repeat migrations
+    debug_assert!(
+        MIGRATIONS.windows(2).all(|pair| pair[1].version == pair[0].version + 1),
+        "Migrations have to be numbered in order without gaps"
+    );
+    let from = schema_version(root)?;
+    let mut report = Report {
+        path: path.to_path_buf(),
+        from,
+        steps: Vec::new(),
+    };
+    for migration in MIGRATIONS.iter().filter(|migration| migration.version > from) {
+        let mut changes = Vec::new();
+        (migration.apply)(root, &mut changes).map_err(|message| MigrationError::Failed {
+            version: migration.version,
+            message,
+        })?;
+        set_value(root, "schema_version", Value::from(i64::from(migration.version)));
+        report.steps.push((migration, changes));
+    }
+    Ok(report)
+}
+
+fn plan(path: &Path) -> Result<Report> {
+    let mut document: DocumentMut = fs::read_to_string(path)?.parse()?;
+    migrate(path, document.as_table_mut())
+}
+
+// Loads the preferences, upgrading the file first if it's outdated
+fn load(path: &Path) -> Result<Preferences> {
+    let original = fs::read_to_string(path)?;
+    let mut document: DocumentMut = original.parse()?;
+    let report = migrate(path, document.as_table_mut())?;
+    let migrated = document.to_string();
+    // Deserialize before touching anything, so a failure leaves no traces
+    let preferences = toml::from_str(&migrated)?;
+    if !report.steps.is_empty() {
+        print!("{}", report);
+        // Keep the original around, in case a migration did something wrong
+        // or the user needs to go back to an older version of the program
+        let backup = write_backup(path, report.from, &original)?;
+        println!("The original was saved as {}", backup.display());
+
+        let temporary = path.with_extension("toml.tmp");
+        fs::write(&temporary, migrated)?;
+        fs::rename(temporary, path)?;
+    }
+    Ok(preferences)
+}
+
+// An earlier backup of the same version may be the only copy of what the
+// user had before, e.g. if they restored it by hand, so it's never replaced
+fn write_backup(path: &Path, version: u32, content: &str) -> io::Result<PathBuf> {
+    for attempt in 0.. {
+        let extension = match attempt {
+            0 => format!("toml.v{}.bak", version),
+            _ => format!("toml.v{}.{}.bak", version, attempt),
+        };
+        let backup = path.with_extension(extension);
+        match OpenOptions::new().write(true).create_new(true).open(&backup) {
+            Ok(mut file) => {
+                file.write_all(content.as_bytes())?;
+                return Ok(backup);
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    unreachable!("There is always a free file name")
+}
+
+// The building blocks for migrations. Each one does nothing if
+// the value it works on isn't there, so migrations can safely
+// run on files that never had the setting in the first place
+
+fn rename(root: &mut Table, from: &str, to: &str, changes: &mut Vec<String>) -> result::Result<(), String> {
+    if get_mut(root, to).is_some() {
+        return Err(format!("can't move {} to {}, it already exists", from, to));
+    }
+    if get_mut(root, from).is_none() {
+        return Ok(());
+    }
+    match (from.rsplit_once('.'), to.rsplit_once('.')) {
+        // Staying in the same table, the key keeps its place and its comments
+        (Some((parent, old)), Some((new_parent, new))) if parent == new_parent => {
+            let table = get_mut(root, parent)
+                .and_then(Item::as_table_like_mut)
+                .expect("Found the value above");
+            rename_key(table, old, new);
+        }
+        (None, None) => rename_key(root, from, to),
+        _ => {
+            let item = take(root, from).expect("Found the value above");
+            insert(root, to, item)?;
+        }
+    }
+    changes.push(format!("moved {} to {}", from, to));
+    Ok(())
+}
+
+fn change_type<F>(root: &mut Table, path: &str, changes: &mut Vec<String>, convert: F) -> result::Result<(), String>
+where
+    F: FnOnce(&Value) -> result::Result<Value, String>,
+{
+    if let Some(item) = get_mut(root, path) {
+        let value = item
+            .as_value_mut()
+            .ok_or_else(|| format!("{}: expected a value, found a table", path))?;
+        let mut converted = convert(value).map_err(|message| format!("{}: {}", path, message))?;
+        changes.push(format!("changed {} from {} to {}", path, show(value), show(&converted)));
+        // Keep any comment that was attached to the old value
+        *converted.decor_mut() = value.decor().clone();
+        *value = converted;
+    }
+    Ok(())
+}
+
+fn set_default(root: &mut Table, path: &str, value: Value, changes: &mut Vec<String>) -> result::Result<(), String> {
+    if get_mut(root, path).is_none() {
+        changes.push(format!("set {} to {}", path, show(&value)));
+        insert(root, path, Item::Value(value))?;
+    }
+    Ok(())
+}
+
+fn get_mut<'a>(root: &'a mut Table, path: &str) -> Option<&'a mut Item> {
+    let mut keys = path.split('.');
+    let mut item = root.get_mut(keys.next()?)?;
+    for key in keys {
+        item = item.as_table_like_mut()?.get_mut(key)?;
+    }
+    Some(item)
+}
+
+fn take(root: &mut Table, path: &str) -> Option<Item> {
+    match path.rsplit_once('.') {
+        Some((parent, key)) => get_mut(root, parent)?.as_table_like_mut()?.remove(key),
+        None => root.remove(path),
+    }
+}
+
+// Creates the tables on the way if needed
+fn insert(root: &mut Table, path: &str, item: Item) -> result::Result<(), String> {
+    let mut keys: Vec<&str> = path.split('.').collect();
+    let last = keys.pop().expect("split always yields at least one part");
+    let mut table: &mut dyn TableLike = root;
+    let mut inline = false;
+    for key in keys {
+        if !table.contains_key(key) {
+            // A new table gets a [header] of its own, unless its parent is inline
+            let new_table = if inline {
+                Item::Value(Value::InlineTable(InlineTable::new()))
+            } else {
+                let mut new_table = Table::new();
+                new_table.set_implicit(true);
+                Item::Table(new_table)
+            };
+            table.insert(key, new_table);
+        }
+        let child = table.get_mut(key).expect("Inserted above");
+        inline = child.is_inline_table();
+        table = child
+            .as_table_like_mut()
+            .ok_or_else(|| format!("can't create {}, {} is not a table", path, key))?;
+    }
+    table.insert(last, item);
+    Ok(())
+}
+
+// Removing and inserting would move the key to the end of its table
+// and lose the comments above it, so the table is rebuilt instead
+fn rename_key(table: &mut dyn TableLike, from: &str, to: &str) {
+    let entries: Vec<(Key, Item)> = table
+        .iter()
+        .map(|(key, item)| (table.key(key).expect("Every entry has a key").clone(), item.clone()))
+        .collect();
+    table.clear();
+    for (key, item) in entries {
+        let key = if key.get() == from {
+            Key::new(to)
+                .with_leaf_decor(key.leaf_decor().clone())
+                .with_dotted_decor(key.dotted_decor().clone())
+        } else {
+            key
+        };
+        table.entry_format(&key).or_insert(item);
+    }
+}
+
+// Replaces a value at the top level, or adds it if it's missing
+fn set_value(root: &mut Table, key: &str, mut value: Value) {
+    match root.get_mut(key).and_then(Item::as_value_mut) {
+        Some(old) => {
+            *value.decor_mut() = old.decor().clone();
+            *old = value;
+        }
+        None => {
+            root.insert(key, Item::Value(value));
+        }
+    }
+}
+
+// A value as it would appear in the file, but without comments and padding
+fn show(value: &Value) -> String {
+    let mut value = value.clone();
+    value.decor_mut().clear();
+    value.to_string()
+}
+
+fn show_item(item: &Item) -> String {
+    match item.as_value() {
+        Some(value) => show(value),
+        None => item.to_string().trim().to_string(),
+    }
+}
@@ -0,0 +1,274 @@
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::de::DeserializeOwned;
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::marker::PhantomData;
+use std::path::Path;
+use std::{env, error, fmt, process};
+
+// A single entry of an event log
+#[derive(Debug, Serialize, Deserialize)]
+struct Event {
+    id: u32,
+    kind: String,
+    pet: String,
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+    if let Some(path) = args.next() {
+        let max_errors = match (args.next().as_deref(), args.next()) {
+            (None, _) => None,
+            (Some("--max-errors"), Some(max)) => Some(max.parse().unwrap_or_else(|_| usage())),
+            _ => usage(),
+        };
+        if let Err(e) = print_records(&path, max_errors) {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
+
+    let directory = env::temp_dir();
+    let plain = directory.join("events.ndjson");
+    let compressed = directory.join("events.ndjson.gz");
+
+    // Every record is flushed right away, so tail -f shows it immediately
+    let mut writer = NdjsonWriter::new(File::create(&plain).expect("Failed to create event log"));
+    let kinds = ["fed", "walked", "fed", "vet visit"];
+    let pets = ["Waldo", "Waldo", "Speedy", "Meows"];
+    for (id, (kind, pet)) in kinds.iter().zip(pets.iter()).enumerate() {
+        let event = Event {
+            id: id as u32,
+            kind: kind.to_string(),
+            pet: pet.to_string(),
+        };
+        writer.write(&event).expect("Failed to write event");
+    }
+    // Simulate a crash in the middle of a write, and a record from a buggy producer
+    let mut file = OpenOptions::new().append(true).open(&plain).expect("Failed to open event log");
+    file.write_all(b"{\"id\": 4, \"kind\": \"fed\", \"pe\n{\"id\": \"five\", \"kind\": \"fed\", \"pet\": \"Waldo\"}\n")
+        .expect("Failed to append to event log");
+    let mut writer = NdjsonWriter::new(file);
+    writer
+        .write(&Event {
+            id: 6,
+            kind: "groomed".to_string(),
+            pet: "Meows".to_string(),
+        })
+        .expect("Failed to write event");
+
+    // Event logs compress well, so older ones are usually kept gzipped
+    let mut encoder = GzEncoder::new(File::create(&compressed).expect("Failed to create file"), Compression::default());
+    io::copy(&mut File::open(&plain).expect("Failed to open event log"), &mut encoder).expect("Failed to compress");
+    encoder.finish().expect("Failed to finish compressing");
+
+    for path in &[&plain, &compressed] {
+        println!("Reading {}", path.display());
+        let reader = NdjsonReader::<_, Event>::open(path).expect("Failed to open event log");
+        for record in reader.max_errors(Some(5)) {
+            match record {
+                Ok(event) => println!("  {:?}", event),
+                Err(e) => println!("  Skipped: {}", e),
+            }
+        }
+    }
+
+    println!("Reading {} without tolerating a single error", plain.display());
+    let reader = NdjsonReader::<_, Event>::open(&plain).expect("Failed to open event log");
+    let events: Result<Vec<Event>, NdjsonError> = reader.max_errors(Some(0)).collect();
+    if let Err(e) = events {
+        println!("  Failed: {}", e);
+    }
+}
+
+fn usage() -> ! {
+    eprintln!(
+        "Usage: ndjson [<file> [--max-errors <count>]]\n\
+         \n\
+         Prints every record of a newline-delimited JSON file, which may be gzipped.\n\
+         Without a file, writes and reads back a small event log."
+    );
+    process::exit(2);
+}
+
+fn print_records(path: &str, max_errors: Option<usize>) -> Result<(), NdjsonError> {
+    let reader = NdjsonReader::<_, Value>::open(path)?.max_errors(max_errors);
+    for record in reader {
+        match record {
+            Ok(record) => println!("{}", record),
+            // Bad lines are reported, but don't stop us
+            Err(e @ NdjsonError::Parse { .. }) => eprintln!("Skipped: {}", e),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+// Our custom error, following the pattern from chapter six
+#[derive(Debug)]
+enum NdjsonError {
+    Io(io::Error),
+    // Line numbers start at 1, like in every editor
+    Parse { line: usize, error: serde_json::Error },
+    // The malformed line that went over the limit
+    TooManyErrors {
+        limit: usize,
+        line: usize,
+        error: serde_json::Error,
+    },
+}
+
+impl error::Error for NdjsonError {
+    fn cause(&self) -> Option<&dyn error::Error> {
+        match *self {
+            NdjsonError::Io(ref err) => Some(err),
+            NdjsonError::Parse { ref error, .. } => Some(error),
+            NdjsonError::TooManyErrors { ref error, .. } => Some(error),
+        }
+    }
+}
+
+impl fmt::Display for NdjsonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            NdjsonError::Io(ref err) => write!(f, "IO error: {}", err),
+            NdjsonError::Parse { line, ref error } => write!(f, "Line {}: {}", line, error),
+            NdjsonError::TooManyErrors { limit, line, ref error } => write!(
+                f,
+                "Line {}: {} (giving up, only {} malformed lines are allowed)",
+                line, error, limit
+            ),
+        }
+    }
+}
+
+impl From<io::Error> for NdjsonError {
+    fn from(err: io::Error) -> NdjsonError {
+        NdjsonError::Io(err)
+    }
+}
+
+// Reads one record per line, without ever holding more than one line in memory
+struct NdjsonReader<R, T> {
+    reader: R,
+    line: usize,
+    buffer: Vec<u8>,
+    errors: usize,
+    // None means any number of malformed lines is fine
+    max_errors: Option<usize>,
+    done: bool,
+    record: PhantomData<T>,
+}
+
+impl<T: DeserializeOwned> NdjsonReader<Box<dyn BufRead>, T> {
+    // Gzipped files are recognized by their magic number, whatever their name
+    fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let is_gzip = reader.fill_buf()?.starts_with(&[0x1f, 0x8b]);
+        let reader: Box<dyn BufRead> = if is_gzip {
+            Box::new(BufReader::new(MultiGzDecoder::new(reader)))
+        } else {
+            Box::new(reader)
+        };
+        Ok(NdjsonReader::new(reader))
+    }
+}
+
+impl<R: BufRead, T: DeserializeOwned> NdjsonReader<R, T> {
+    fn new(reader: R) -> Self {
+        NdjsonReader {
+            reader,
+            line: 0,
+            buffer: Vec::new(),
+            errors: 0,
+            max_errors: None,
+            done: false,
+            record: PhantomData,
+        }
+    }
+
+    fn max_errors(mut self, max_errors: Option<usize>) -> Self {
+        self.max_errors = max_errors;
+        self
+    }
+}
+
+impl<R: BufRead, T: DeserializeOwned> Iterator for NdjsonReader<R, T> {
+    type Item = Result<T, NdjsonError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            self.buffer.clear();
+            // Reading bytes instead of a String means invalid UTF-8 is
+            // just another malformed line and not a fatal IO error
+            match self.reader.read_until(b'\n', &mut self.buffer) {
+                Ok(0) => self.done = true,
+                Ok(_) => {
+                    self.line += 1;
+                    // Without the newline, errors point at the end of the line and not past it
+                    if self.buffer.ends_with(b"\n") {
+                        self.buffer.pop();
+                    }
+                    if self.buffer.iter().all(u8::is_ascii_whitespace) {
+                        continue;
+                    }
+                    match serde_json::from_slice(&self.buffer) {
+                        Ok(record) => return Some(Ok(record)),
+                        Err(error) => {
+                            self.errors += 1;
+                            if let Some(limit) = self.max_errors {
+                                if self.errors > limit {
+                                    self.done = true;
+                                    return Some(Err(NdjsonError::TooManyErrors {
+                                        limit,
+                                        line: self.line,
+                                        error,
+                                    }));
+                                }
+                            }
+                            return Some(Err(NdjsonError::Parse { line: self.line, error }));
+                        }
+                    }
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e.into()));
+                }
+            }
+        }
+        None
+    }
+}
+
+struct NdjsonWriter<W: Write> {
+    writer: W,
+    // Reused for every record, so writing doesn't allocate
+    line: Vec<u8>,
+}
+
+impl<W: Write> NdjsonWriter<W> {
+    fn new(writer: W) -> Self {
+        NdjsonWriter {
+            writer,
+            line: Vec::new(),
+        }
+    }
+
+    // The whole line, newline included, is handed over in one write_all.
+    // A record that fails to serialize never reaches the writer at all,
+    // and readers following the file don't see a record without its newline
+    fn write<T: serde::Serialize>(&mut self, record: &T) -> Result<(), NdjsonError> {
+        self.line.clear();
+        serde_json::to_writer(&mut self.line, record).map_err(io::Error::from)?;
+        self.line.push(b'\n');
+        self.writer.write_all(&self.line)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
@@ -0,0 +1,364 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::{env, error, fmt, io, process, result};
+use toml_edit::{DocumentMut, InlineTable, Item, Key, Table, Value};
+
+// The same preferences as written by toml.rs, after the user had a go at them
+const EDITED_PREFERENCES: &str = r#"# My settings, please don't touch
+[person]
+name = "Jan Nils Ferner"
+email = "jn_ferner@hotmail.de"   # work address
+
+[language]
+display = "en-GB"
+autocorrect = ["en-GB", "en-US", "de-CH"]
+
+# Decided on these after reading the privacy policy
+[privacy]
+share_anonymous_statistics = false
+public_name = true
+public_email = true
+"#;
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.is_empty() {
+        demo();
+        return;
+    }
+    if let Err(e) = run(&args) {
+        eprintln!("Error: {}", e);
+        if let EditError::Usage(_) = e {
+            eprintln!(
+                "\nUsage: toml_editor <file> get <key.path>\n       \
+                 toml_editor <file> set <key.path> <value> [--force]\n       \
+                 toml_editor <file> remove <key.path>\n\
+                 \n\
+                 Values are parsed as TOML, so true, 42 and [\"a\", \"b\"] keep their type.\n\
+                 Anything that isn't valid TOML is stored as a string.\n\
+                 Replacing a whole table with a value needs --force."
+            );
+            process::exit(2);
+        }
+        process::exit(1);
+    }
+}
+
+fn run(args: &[String]) -> Result<()> {
+    let (path, command, key_path) = match args {
+        [path, command, key_path, ..] => (path, command.as_str(), key_path),
+        _ => return Err(EditError::Usage("Expected a file, a command and a key path".to_string())),
+    };
+    let mut file = TomlFile::open(path)?;
+    match (command, &args[3..]) {
+        ("get", []) => match file.get(key_path)? {
+            Some(item) => println!("{}", show(item)),
+            None => return Err(EditError::Missing(key_path.clone())),
+        },
+        ("set", [value]) => {
+            file.set(key_path, parse_value(value))?;
+            file.save()?;
+        }
+        ("set", [value, force]) if force == "--force" => {
+            file.overwrite(key_path, parse_value(value))?;
+            file.save()?;
+        }
+        ("remove", []) => {
+            if file.remove(key_path)?.is_none() {
+                return Err(EditError::Missing(key_path.clone()));
+            }
+            file.save()?;
+        }
+        _ => return Err(EditError::Usage(format!("Invalid command '{}'", args[1..].join(" ")))),
+    }
+    Ok(())
+}
+
+fn demo() {
+    let mut preferences = TomlFile::from_str("preferences.toml", EDITED_PREFERENCES).expect("Failed to parse TOML");
+
+    let email = preferences.get("person.email").expect("Invalid path");
+    println!("person.email is {}", email.map(show).unwrap_or_default());
+    let language = preferences.get("language.autocorrect.1").expect("Invalid path");
+    println!("The second autocorrect language is {}", language.map(show).unwrap_or_default());
+
+    // Existing values keep their comments, new ones go where they belong
+    preferences.set("privacy.public_email", false).expect("Failed to set value");
+    preferences.set("person.email", "jan@example.com").expect("Failed to set value");
+    preferences.set("language.autocorrect.2", "fr-CH").expect("Failed to set value");
+    preferences.set("person.birthday", "1996-02-29").expect("Failed to set value");
+    preferences.set("notifications.email.weekly_digest", true).expect("Failed to set value");
+    let removed = preferences.remove("privacy.public_name").expect("Failed to remove value");
+    println!("Removed privacy.public_name, which was {}", removed.as_ref().map(show).unwrap_or_default());
+
+    // Mistakes are reported instead of silently replacing data
+    if let Err(e) = preferences.set("person.name.first", "Jan") {
+        println!("Setting person.name.first failed: {}", e);
+    }
+    if let Err(e) = preferences.get("language..display") {
+        println!("Getting language..display failed: {}", e);
+    }
+
+    println!("\nThe edited file:\n{}", preferences);
+}
+
+// Our custom error, following the pattern from chapter six
+#[derive(Debug)]
+enum EditError {
+    Io(io::Error),
+    Parse(toml_edit::TomlError),
+    // The key path itself isn't valid, like "a..b"
+    InvalidPath(String),
+    // Something on the way to the key isn't a table or an array
+    NotATable(String),
+    // Setting a value would throw away a whole table
+    IsATable(String),
+    Missing(String),
+    Usage(String),
+}
+
+type Result<T> = result::Result<T, EditError>;
+
+impl error::Error for EditError {
+    fn cause(&self) -> Option<&dyn error::Error> {
+        match *self {
+            EditError::Io(ref err) => Some(err),
+            EditError::Parse(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            EditError::Io(ref err) => write!(f, "IO error: {}", err),
+            EditError::Parse(ref err) => write!(f, "Parse error: {}", err),
+            EditError::InvalidPath(ref path) => write!(f, "'{}' is not a valid key path", path),
+            EditError::NotATable(ref path) => write!(f, "'{}' is a value, not a table or array", path),
+            EditError::IsATable(ref path) => write!(f, "'{}' is a table, use --force to replace it", path),
+            EditError::Missing(ref path) => write!(f, "'{}' doesn't exist", path),
+            EditError::Usage(ref message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<io::Error> for EditError {
+    fn from(err: io::Error) -> EditError {
+        EditError::Io(err)
+    }
+}
+
+impl From<toml_edit::TomlError> for EditError {
+    fn from(err: toml_edit::TomlError) -> EditError {
+        EditError::Parse(err)
+    }
+}
+
+// A TOML file that remembers its comments, whitespace and key order.
+// Only the values we touch change, everything else is written back byte for byte
+struct TomlFile {
+    path: PathBuf,
+    document: DocumentMut,
+}
+
+impl TomlFile {
+    fn open<P: AsRef<Path>>(path: P) -> Result<TomlFile> {
+        let content = fs::read_to_string(&path)?;
+        TomlFile::from_str(path, &content)
+    }
+
+    fn from_str<P: AsRef<Path>>(path: P, content: &str) -> Result<TomlFile> {
+        Ok(TomlFile {
+            path: path.as_ref().to_path_buf(),
+            document: content.parse()?,
+        })
+    }
+
+    fn get(&self, path: &str) -> Result<Option<&Item>> {
+        let mut item = self.document.as_item();
+        for segment in parse_path(path)? {
+            item = match lookup(item, segment.get()) {
+                Some(next) => next,
+                None => return Ok(None),
+            };
+        }
+        Ok(Some(item))
+    }
+
+    // Creates any missing tables on the way, but never replaces an existing table
+    fn set<V: Into<Value>>(&mut self, path: &str, value: V) -> Result<()> {
+        self.insert(path, value, false)
+    }
+
+    // Like set, but a table at the path is replaced along with everything in it
+    fn overwrite<V: Into<Value>>(&mut self, path: &str, value: V) -> Result<()> {
+        self.insert(path, value, true)
+    }
+
+    fn insert<V: Into<Value>>(&mut self, path: &str, value: V, replace_tables: bool) -> Result<()> {
+        let mut segments = parse_path(path)?;
+        let last = segments.pop().expect("Paths have at least one segment");
+        let mut item = self.document.as_item_mut();
+        for (depth, segment) in segments.iter().enumerate() {
+            if let Some(index) = array_index(item, segment.get()) {
+                item = item
+                    .get_mut(index)
+                    .ok_or_else(|| EditError::Missing(join_path(&segments[..=depth])))?;
+                continue;
+            }
+            // A new table is written as [section] at the top level, but
+            // has to be inline if its parent is already an inline table
+            let new_table = if item.is_table() {
+                // Implicit tables don't get a header of their own if
+                // all they contain are other tables, just like [a.b]
+                let mut table = Table::new();
+                table.set_implicit(true);
+                Item::Table(table)
+            } else if item.is_inline_table() {
+                Item::Value(Value::InlineTable(InlineTable::new()))
+            } else {
+                return Err(EditError::NotATable(join_path(&segments[..depth])));
+            };
+            let table = item.as_table_like_mut().expect("Checked above");
+            if table.get(segment.get()).is_none() {
+                table.insert(segment.get(), new_table);
+            }
+            item = table.get_mut(segment.get()).expect("Inserted above");
+        }
+
+        let slot = match array_index(item, last.get()) {
+            Some(index) => {
+                // One past the end appends a new element
+                let array = item.as_array_mut().expect("array_index only accepts arrays");
+                if index == array.len() {
+                    array.push(value);
+                    return Ok(());
+                }
+                item
+                    .get_mut(index)
+                    .ok_or_else(|| EditError::Missing(path.to_string()))?
+            }
+            None => {
+                let table = item
+                    .as_table_like_mut()
+                    .ok_or_else(|| EditError::NotATable(join_path(&segments)))?;
+                match table.get(last.get()) {
+                    None => {
+                        table.insert(last.get(), Item::Value(value.into()));
+                        return Ok(());
+                    }
+                    // The key of a [section] header is spaced for the header, not for a value
+                    Some(existing) if replace_tables && existing.is_table() => {
+                        if let Some(mut key) = table.key_mut(last.get()) {
+                            key.leaf_decor_mut().clear();
+                        }
+                    }
+                    Some(_) => {}
+                }
+                table.get_mut(last.get()).expect("Checked above")
+            }
+        };
+        if !replace_tables && (slot.is_table_like() || slot.is_array_of_tables()) {
+            return Err(EditError::IsATable(path.to_string()));
+        }
+        let mut value = value.into();
+        // Keep any comment that was attached to the old value
+        if let Some(old) = slot.as_value() {
+            *value.decor_mut() = old.decor().clone();
+        }
+        *slot = Item::Value(value);
+        Ok(())
+    }
+
+    fn remove(&mut self, path: &str) -> Result<Option<Item>> {
+        let mut segments = parse_path(path)?;
+        let last = segments.pop().expect("Paths have at least one segment");
+        let mut item = self.document.as_item_mut();
+        for segment in &segments {
+            item = match lookup_mut(item, segment.get()) {
+                Some(next) => next,
+                None => return Ok(None),
+            };
+        }
+        if let Some(index) = array_index(item, last.get()) {
+            let array = item.as_array_mut().expect("array_index only accepts arrays");
+            if index >= array.len() {
+                return Ok(None);
+            }
+            return Ok(Some(Item::Value(array.remove(index))));
+        }
+        match item.as_table_like_mut() {
+            Some(table) => Ok(table.remove(last.get())),
+            None => Err(EditError::NotATable(join_path(&segments))),
+        }
+    }
+
+    // Write to a temporary file first and rename it, so a crash
+    // never leaves a half-written file behind
+    fn save(&self) -> Result<()> {
+        let temporary = self.path.with_extension("toml.tmp");
+        fs::write(&temporary, self.document.to_string())?;
+        fs::rename(temporary, &self.path)?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for TomlFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.document)
+    }
+}
+
+// "privacy.public_email" or "servers.\"eu.west\".ip", quoted just like in TOML
+fn parse_path(path: &str) -> Result<Vec<Key>> {
+    Key::parse(path).map_err(|_| EditError::InvalidPath(path.to_string()))
+}
+
+fn join_path(segments: &[Key]) -> String {
+    segments
+        .iter()
+        .map(|segment| segment.display_repr().into_owned())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+// Numbers in a path index into arrays, but only if there is an array to index
+fn array_index(item: &Item, segment: &str) -> Option<usize> {
+    if item.is_array() {
+        segment.parse().ok()
+    } else {
+        None
+    }
+}
+
+fn lookup<'a>(item: &'a Item, segment: &str) -> Option<&'a Item> {
+    match array_index(item, segment) {
+        Some(index) => item.get(index),
+        None => item.get(segment),
+    }
+}
+
+// Unlike Item::get_mut, this never inserts an empty entry for missing keys
+fn lookup_mut<'a>(item: &'a mut Item, segment: &str) -> Option<&'a mut Item> {
+    match array_index(item, segment) {
+        Some(index) => item.get_mut(index),
+        None => item.as_table_like_mut()?.get_mut(segment),
+    }
+}
+
+// An item as it would appear in the file, but without comments and padding
+fn show(item: &Item) -> String {
+    match item.as_value() {
+        Some(value) => {
+            let mut value = value.clone();
+            value.decor_mut().clear();
+            value.to_string()
+        }
+        None => item.to_string().trim().to_string(),
+    }
+}
+
+fn parse_value(input: &str) -> Value {
+    input.parse().unwrap_or_else(|_| Value::from(input))
+}
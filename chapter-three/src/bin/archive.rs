@@ -0,0 +1,466 @@
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+use flate2::Compression;
+use flate2::Crc;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use walkdir::WalkDir;
+
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+use std::process;
+use std::time::{Duration, UNIX_EPOCH};
+
+// Layout of an archive:
+//
+//   "RSARCHV1"                       magic, 8 bytes
+//   entry data ...                   every file deflated on its own
+//   index                            one record per entry, see IndexEntry
+//   index offset (u64), "RSINDEX1"   footer, 16 bytes
+//
+// Because the index sits at a known place at the end of the file,
+// listing needs a single seek and extracting one file never
+// touches the data of any other file.
+const MAGIC: &[u8; 8] = b"RSARCHV1";
+const INDEX_MAGIC: &[u8; 8] = b"RSINDEX1";
+const FOOTER_SIZE: i64 = 16;
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["create", archive, dir] => create(Path::new(archive), Path::new(dir)),
+        ["list", archive] => list(Path::new(archive)),
+        ["extract", archive, dest, paths @ ..] => extract(Path::new(archive), Path::new(dest), paths),
+        ["verify", archive] => verify(Path::new(archive)),
+        _ => {
+            eprintln!(
+                "Usage:\n  \
+                 archive create <archive> <directory>\n  \
+                 archive list <archive>\n  \
+                 archive extract <archive> <destination> [paths...]\n  \
+                 archive verify <archive>"
+            );
+            process::exit(2);
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum EntryKind {
+    File = 0,
+    Directory = 1,
+}
+
+#[derive(Debug)]
+struct IndexEntry {
+    // Always relative and separated by '/', no matter the OS
+    path: String,
+    kind: EntryKind,
+    mode: u32,
+    mtime: u64,
+    size: u64,
+    compressed_size: u64,
+    offset: u64,
+    crc: u32,
+}
+
+impl IndexEntry {
+    fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let path = self.path.as_bytes();
+        if path.len() > u16::MAX as usize {
+            return Err(invalid_data(format!("Path is too long: {}", self.path)));
+        }
+        writer.write_u16::<LE>(path.len() as u16)?;
+        writer.write_all(path)?;
+        writer.write_u8(self.kind as u8)?;
+        writer.write_u32::<LE>(self.mode)?;
+        writer.write_u64::<LE>(self.mtime)?;
+        writer.write_u64::<LE>(self.size)?;
+        writer.write_u64::<LE>(self.compressed_size)?;
+        writer.write_u64::<LE>(self.offset)?;
+        writer.write_u32::<LE>(self.crc)
+    }
+
+    fn read_from<R: Read>(reader: &mut R) -> io::Result<IndexEntry> {
+        let path_len = reader.read_u16::<LE>()?;
+        let mut path = vec![0; path_len as usize];
+        reader.read_exact(&mut path)?;
+        let path = String::from_utf8(path).map_err(|_| invalid_data("Path is not valid UTF-8"))?;
+        let kind = match reader.read_u8()? {
+            0 => EntryKind::File,
+            1 => EntryKind::Directory,
+            other => return Err(invalid_data(format!("Unknown entry kind {}", other))),
+        };
+        Ok(IndexEntry {
+            path,
+            kind,
+            mode: reader.read_u32::<LE>()?,
+            mtime: reader.read_u64::<LE>()?,
+            size: reader.read_u64::<LE>()?,
+            compressed_size: reader.read_u64::<LE>()?,
+            offset: reader.read_u64::<LE>()?,
+            crc: reader.read_u32::<LE>()?,
+        })
+    }
+}
+
+fn invalid_data<E>(error: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+// Counts the bytes the encoder produces, so we know each entry's size
+struct CountingWriter<W> {
+    inner: W,
+    count: u64,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.count += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// Feeds everything that is read through a CRC32 checksum
+struct CrcReader<R> {
+    inner: R,
+    crc: Crc,
+}
+
+impl<R: Read> Read for CrcReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.crc.update(&buf[..read]);
+        Ok(read)
+    }
+}
+
+fn create(archive: &Path, dir: &Path) -> io::Result<()> {
+    let mut writer = CountingWriter {
+        inner: BufWriter::new(File::create(archive)?),
+        count: 0,
+    };
+    writer.write_all(MAGIC)?;
+
+    let mut index = Vec::new();
+    // Sorting gives us reproducible archives
+    for entry in WalkDir::new(dir).min_depth(1).sort_by_file_name() {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        let relative = entry
+            .path()
+            .strip_prefix(dir)
+            .expect("WalkDir returned a path outside of its root");
+        let path = archive_path(relative)?;
+
+        let kind = if metadata.is_dir() {
+            EntryKind::Directory
+        } else if metadata.is_file() {
+            EntryKind::File
+        } else {
+            // Symlinks and special files could point anywhere,
+            // so we leave them out instead of following them
+            eprintln!("Skipping {}: not a regular file or directory", entry.path().display());
+            continue;
+        };
+        let mut index_entry = IndexEntry {
+            path,
+            kind,
+            mode: mode_of(&metadata),
+            mtime: metadata
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|duration| duration.as_secs())
+                .unwrap_or(0),
+            size: 0,
+            compressed_size: 0,
+            offset: writer.count,
+            crc: 0,
+        };
+
+        if kind == EntryKind::File {
+            let mut reader = CrcReader {
+                inner: BufReader::new(File::open(entry.path())?),
+                crc: Crc::new(),
+            };
+            let start = writer.count;
+            // Every entry gets its own deflate stream,
+            // which is what makes random access possible
+            let mut encoder = DeflateEncoder::new(&mut writer, Compression::default());
+            index_entry.size = io::copy(&mut reader, &mut encoder)?;
+            encoder.finish()?;
+            index_entry.compressed_size = writer.count - start;
+            index_entry.crc = reader.crc.sum();
+        }
+        println!("Adding {}", index_entry.path);
+        index.push(index_entry);
+    }
+
+    let index_offset = writer.count;
+    writer.write_u32::<LE>(index.len() as u32)?;
+    for entry in &index {
+        entry.write_to(&mut writer)?;
+    }
+    writer.write_u64::<LE>(index_offset)?;
+    writer.write_all(INDEX_MAGIC)?;
+    writer.flush()?;
+    println!("Wrote {} entries to {}", index.len(), archive.display());
+    Ok(())
+}
+
+// Turn an OS path into the portable form we store in the index
+fn archive_path(path: &Path) -> io::Result<String> {
+    let mut parts = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => parts.push(
+                part.to_str()
+                    .ok_or_else(|| invalid_data(format!("Path is not valid UTF-8: {}", path.display())))?,
+            ),
+            _ => return Err(invalid_data(format!("Unexpected path: {}", path.display()))),
+        }
+    }
+    Ok(parts.join("/"))
+}
+
+#[cfg(unix)]
+fn mode_of(metadata: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o7777
+}
+
+#[cfg(not(unix))]
+fn mode_of(metadata: &fs::Metadata) -> u32 {
+    if metadata.permissions().readonly() {
+        0o444
+    } else {
+        0o644
+    }
+}
+
+fn read_index(file: &mut File) -> io::Result<Vec<IndexEntry>> {
+    let mut magic = [0; 8];
+    file.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid_data("Not an archive: magic number doesn't match"));
+    }
+
+    // The footer tells us where the index starts
+    let archive_len = file.seek(SeekFrom::End(-FOOTER_SIZE))?;
+    let index_offset = file.read_u64::<LE>()?;
+    file.read_exact(&mut magic)?;
+    if &magic != INDEX_MAGIC || index_offset > archive_len {
+        return Err(invalid_data("Archive is truncated or its index is damaged"));
+    }
+
+    file.seek(SeekFrom::Start(index_offset))?;
+    let mut reader = BufReader::new(file.take(archive_len - index_offset));
+    let count = reader.read_u32::<LE>()?;
+    let mut index = Vec::new();
+    for _ in 0..count {
+        let entry = IndexEntry::read_from(&mut reader)?;
+        let end = entry.offset.checked_add(entry.compressed_size);
+        if end.is_none_or(|end| end > index_offset) {
+            return Err(invalid_data(format!("Entry {} points outside of the archive", entry.path)));
+        }
+        index.push(entry);
+    }
+    Ok(index)
+}
+
+fn list(archive: &Path) -> io::Result<()> {
+    let index = read_index(&mut File::open(archive)?)?;
+    println!("{:>6} {:>12} {:>12} {:>12}  path", "mode", "size", "compressed", "mtime");
+    for entry in &index {
+        let suffix = if entry.kind == EntryKind::Directory { "/" } else { "" };
+        println!(
+            "{:>6o} {:>12} {:>12} {:>12}  {}{}",
+            entry.mode, entry.size, entry.compressed_size, entry.mtime, entry.path, suffix
+        );
+    }
+    println!("{} entries", index.len());
+    Ok(())
+}
+
+// Open a reader over the decompressed contents of a single entry
+fn entry_reader<'a>(file: &'a mut File, entry: &IndexEntry) -> io::Result<CrcReader<impl Read + 'a>> {
+    file.seek(SeekFrom::Start(entry.offset))?;
+    let compressed = BufReader::new(file.take(entry.compressed_size));
+    Ok(CrcReader {
+        inner: DeflateDecoder::new(compressed),
+        crc: Crc::new(),
+    })
+}
+
+fn check_entry<R: Read>(reader: &CrcReader<R>, entry: &IndexEntry, size: u64) -> io::Result<()> {
+    if size != entry.size || reader.crc.sum() != entry.crc {
+        return Err(invalid_data(format!("{} is corrupted: checksum mismatch", entry.path)));
+    }
+    Ok(())
+}
+
+fn verify(archive: &Path) -> io::Result<()> {
+    let mut file = File::open(archive)?;
+    let index = read_index(&mut file)?;
+    let mut failures = 0;
+    for entry in index.iter().filter(|entry| entry.kind == EntryKind::File) {
+        let result = entry_reader(&mut file, entry).and_then(|mut reader| {
+            let size = io::copy(&mut reader, &mut io::sink())?;
+            check_entry(&reader, entry, size)
+        });
+        match result {
+            Ok(()) => println!("OK      {}", entry.path),
+            Err(e) => {
+                println!("FAILED  {}: {}", entry.path, e);
+                failures += 1;
+            }
+        }
+    }
+    if failures > 0 {
+        return Err(invalid_data(format!("{} entries failed verification", failures)));
+    }
+    println!("All {} entries are intact", index.len());
+    Ok(())
+}
+
+// Resolve a path from the index below the destination directory.
+// Archives can come from anywhere, so a malicious one could contain
+// paths like "../../.bashrc" or "/etc/passwd". We refuse those.
+fn safe_destination(dest: &Path, path: &str) -> io::Result<PathBuf> {
+    let mut resolved = dest.to_path_buf();
+    for part in path.split('/') {
+        let is_safe = !part.is_empty()
+            && part != "."
+            && part != ".."
+            && !part.contains('\\')
+            && !part.contains(':')
+            && !part.contains('\0');
+        if !is_safe {
+            return Err(invalid_data(format!("Refusing to extract unsafe path: {}", path)));
+        }
+        resolved.push(part);
+    }
+    Ok(resolved)
+}
+
+fn extract(archive: &Path, dest: &Path, only: &[&str]) -> io::Result<()> {
+    let mut file = File::open(archive)?;
+    let index = read_index(&mut file)?;
+    // Only extract what was asked for, including everything inside requested directories
+    let wanted = |path: &str| {
+        only.is_empty()
+            || only.iter().any(|prefix| {
+                let prefix = prefix.trim_end_matches('/');
+                path == prefix || path.starts_with(&format!("{}/", prefix))
+            })
+    };
+
+    fs::create_dir_all(dest)?;
+    let mut extracted = 0;
+    let mut directories = Vec::new();
+    for entry in index.iter().filter(|entry| wanted(&entry.path)) {
+        let target = safe_destination(dest, &entry.path)?;
+        // An earlier entry could have been a symlink planted by someone else,
+        // so make sure no parent directory leads outside of the destination
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+            let real_dest = dest.canonicalize()?;
+            if !parent.canonicalize()?.starts_with(&real_dest) {
+                return Err(invalid_data(format!("Refusing to extract through a symlink: {}", entry.path)));
+            }
+        }
+
+        let existing = match fs::symlink_metadata(&target) {
+            Ok(metadata) => Some(metadata),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+        let is_real_directory = existing.as_ref().is_some_and(|metadata| metadata.is_dir());
+
+        match entry.kind {
+            EntryKind::Directory => {
+                // A file or symlink in its place is replaced, not followed
+                if existing.is_some() && !is_real_directory {
+                    fs::remove_file(&target)?;
+                }
+                fs::create_dir_all(&target)?;
+                // A read-only directory would keep us from filling it,
+                // so its permissions are applied at the very end
+                directories.push((target, entry.mode));
+            }
+            EntryKind::File => {
+                if is_real_directory {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AlreadyExists,
+                        format!("Can't extract {}, a directory is in the way", entry.path),
+                    ));
+                }
+                // Only a complete and verified file ever shows up under its real name
+                let mut temporary = target.clone().into_os_string();
+                temporary.push(".partial");
+                let temporary = PathBuf::from(temporary);
+                if let Err(e) = extract_file(&mut file, entry, &temporary) {
+                    let _ = fs::remove_file(&temporary);
+                    return Err(e);
+                }
+                // Renaming replaces a file or symlink at the target
+                // instead of writing through it
+                fs::rename(&temporary, &target)?;
+            }
+        }
+        println!("Extracted {}", entry.path);
+        extracted += 1;
+    }
+    for (directory, mode) in directories.iter().rev() {
+        set_mode(directory, *mode)?;
+    }
+    if extracted == 0 && !only.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "None of the given paths are in the archive"));
+    }
+    Ok(())
+}
+
+fn extract_file(file: &mut File, entry: &IndexEntry, path: &Path) -> io::Result<()> {
+    // A leftover from an earlier run could be a symlink planted by someone else
+    match fs::remove_file(path) {
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => {}
+        result => result?,
+    }
+    let mut reader = entry_reader(file, entry)?;
+    let output = OpenOptions::new().write(true).create_new(true).open(path)?;
+    let mut writer = BufWriter::new(output);
+    let size = io::copy(&mut reader, &mut writer)?;
+    writer.flush()?;
+    check_entry(&reader, entry, size)?;
+    let output = writer.into_inner().map_err(|e| e.into_error())?;
+    output.set_modified(UNIX_EPOCH + Duration::from_secs(entry.mtime))?;
+    set_mode(path, entry.mode)
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    // Never restore setuid, setgid or sticky bits from an untrusted archive
+    fs::set_permissions(path, fs::Permissions::from_mode(mode & 0o777))
+}
+
+#[cfg(not(unix))]
+fn set_mode(path: &Path, mode: u32) -> io::Result<()> {
+    let mut permissions = fs::metadata(path)?.permissions();
+    permissions.set_readonly(mode & 0o200 == 0);
+    fs::set_permissions(path, permissions)
+}
@@ -0,0 +1,384 @@
+use flate2::Compression;
+use flate2::{GzBuilder, GzHeader};
+use flate2::read::{DeflateDecoder, MultiGzDecoder, ZlibDecoder};
+use flate2::write::{DeflateEncoder, ZlibEncoder};
+
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::process;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let options = match Options::parse(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n", e);
+            print_usage();
+            process::exit(2);
+        }
+    };
+
+    let result = match options.mode {
+        Mode::Compress => compress(&options),
+        Mode::Decompress => decompress(&options),
+        Mode::Test => test(&options),
+    };
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
+
+fn print_usage() {
+    eprintln!(
+        "Usage: compression_cli <compress|decompress|test> [options] [input] [output]\n\
+         \n\
+         Options:\n\
+         \x20 -f, --format <gzip|zlib|deflate>  Container format (default: from extension, else gzip)\n\
+         \x20 -l, --level <0-9>                 Compression level (default: 6)\n\
+         \x20 -n, --no-name                     Don't store or restore the file name and mtime of gzip files\n\
+         \n\
+         Use '-' or leave out input / output to read from stdin / write to stdout.\n\
+         A gzip file that stores its original name is decompressed next to itself under that name.\n\
+         Statistics are printed to stderr, so they never mix with the data."
+    );
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    Compress,
+    Decompress,
+    Test,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Gzip,
+    Zlib,
+    Deflate,
+}
+
+impl Format {
+    fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "gzip" | "gz" => Some(Format::Gzip),
+            "zlib" | "zz" => Some(Format::Zlib),
+            "deflate" | "raw" => Some(Format::Deflate),
+            _ => None,
+        }
+    }
+
+    // Guess the format of a file by looking at its extension
+    fn from_path(path: &str) -> Option<Format> {
+        match Path::new(path).extension()?.to_str()? {
+            "gz" | "gzip" => Some(Format::Gzip),
+            "zz" | "zlib" => Some(Format::Zlib),
+            "deflate" => Some(Format::Deflate),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Options {
+    mode: Mode,
+    format: Format,
+    level: Compression,
+    store_name: bool,
+    input: Option<String>,
+    output: Option<String>,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Options, String> {
+        let mode = match args.first().map(String::as_str) {
+            Some("compress") | Some("c") => Mode::Compress,
+            Some("decompress") | Some("d") => Mode::Decompress,
+            Some("test") | Some("t") => Mode::Test,
+            Some(other) => return Err(format!("Unknown mode '{}'", other)),
+            None => return Err("No mode given".to_string()),
+        };
+
+        let mut format = None;
+        let mut level = Compression::default();
+        let mut store_name = true;
+        let mut paths = Vec::new();
+        let mut args = args[1..].iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-f" | "--format" => {
+                    let name = args.next().ok_or("Missing value for --format")?;
+                    format = Some(
+                        Format::from_name(name)
+                            .ok_or_else(|| format!("Unknown format '{}'", name))?,
+                    );
+                }
+                "-l" | "--level" => {
+                    let value = args.next().ok_or("Missing value for --level")?;
+                    let value: u32 = value
+                        .parse()
+                        .map_err(|_| format!("Invalid level '{}'", value))?;
+                    if value > 9 {
+                        return Err(format!("Level must be between 0 and 9, got {}", value));
+                    }
+                    level = Compression::new(value);
+                }
+                "-n" | "--no-name" => store_name = false,
+                "-" => paths.push(None),
+                flag if flag.starts_with('-') => {
+                    return Err(format!("Unknown option '{}'", flag))
+                }
+                path => paths.push(Some(path.to_string())),
+            }
+        }
+        if paths.len() > 2 {
+            return Err("Too many paths given".to_string());
+        }
+        let mut paths = paths.into_iter();
+        let input = paths.next().flatten();
+        let output = paths.next().flatten();
+
+        // When compressing, the output name tells us the format.
+        // Otherwise, the input is the compressed side
+        let compressed_path = match mode {
+            Mode::Compress => output.as_deref(),
+            _ => input.as_deref(),
+        };
+        let format = format
+            .or_else(|| compressed_path.and_then(Format::from_path))
+            .unwrap_or(Format::Gzip);
+
+        Ok(Options {
+            mode,
+            format,
+            level,
+            store_name,
+            input,
+            output,
+        })
+    }
+}
+
+// Wraps a reader or writer and counts how many bytes went through it
+struct Counter<T> {
+    inner: T,
+    count: u64,
+}
+
+impl<T> Counter<T> {
+    fn new(inner: T) -> Self {
+        Counter { inner, count: 0 }
+    }
+}
+
+impl<R: Read> Read for Counter<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.count += read as u64;
+        Ok(read)
+    }
+}
+
+impl<W: Write> Write for Counter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.count += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn open_input(path: &Option<String>) -> io::Result<Box<dyn Read>> {
+    // Boxing lets us treat files and stdin the same way
+    Ok(match path {
+        Some(path) => Box::new(BufReader::new(File::open(path)?)),
+        None => Box::new(BufReader::new(io::stdin())),
+    })
+}
+
+fn open_output(path: &Option<String>) -> io::Result<Box<dyn Write>> {
+    Ok(match path {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout())),
+    })
+}
+
+fn compress(options: &Options) -> io::Result<()> {
+    let start = Instant::now();
+    let mut input = Counter::new(open_input(&options.input)?);
+    let output = Counter::new(open_output(&options.output)?);
+
+    // io::copy moves the data in small chunks,
+    // so memory usage stays the same no matter how big the input is
+    let mut output = match options.format {
+        Format::Gzip => {
+            let mut builder = GzBuilder::new();
+            if options.store_name {
+                if let Some(path) = &options.input {
+                    builder = builder.filename(gzip_name(path)).mtime(modification_time(path));
+                }
+            }
+            let mut encoder = builder.write(output, options.level);
+            io::copy(&mut input, &mut encoder)?;
+            encoder.finish()?
+        }
+        Format::Zlib => {
+            let mut encoder = ZlibEncoder::new(output, options.level);
+            io::copy(&mut input, &mut encoder)?;
+            encoder.finish()?
+        }
+        Format::Deflate => {
+            let mut encoder = DeflateEncoder::new(output, options.level);
+            io::copy(&mut input, &mut encoder)?;
+            encoder.finish()?
+        }
+    };
+    output.flush()?;
+
+    report(input.count, output.count, input.count, start);
+    Ok(())
+}
+
+fn decompress(options: &Options) -> io::Result<()> {
+    let start = Instant::now();
+    let restore = options.format == Format::Gzip && options.store_name;
+    let stored_name = match (&options.input, &options.output) {
+        (Some(input), None) if restore => stored_name(input)?,
+        _ => None,
+    };
+    let mut input = Counter::new(open_input(&options.input)?);
+    let output_path = options.output.clone().or_else(|| stored_name.clone());
+    let output: Box<dyn Write> = match stored_name {
+        // Like gzip -d, never replace a file we only chose the name of
+        Some(ref path) => {
+            eprintln!("Writing to {}", path);
+            let file = OpenOptions::new().write(true).create_new(true).open(path).map_err(|e| {
+                if e.kind() == io::ErrorKind::AlreadyExists {
+                    io::Error::new(e.kind(), format!("{} already exists, give an output path to write elsewhere", path))
+                } else {
+                    e
+                }
+            })?;
+            Box::new(BufWriter::new(file))
+        }
+        None => open_output(&options.output)?,
+    };
+    let mut output = Counter::new(output);
+    let header = decode(options.format, &mut input, &mut output)?;
+    output.flush()?;
+
+    if let (Some(path), Some(header)) = (&output_path, header.filter(|_| restore)) {
+        if header.mtime() != 0 {
+            let mtime = UNIX_EPOCH + Duration::from_secs(header.mtime().into());
+            OpenOptions::new().write(true).open(path)?.set_modified(mtime)?;
+        }
+    }
+
+    report(output.count, input.count, output.count, start);
+    Ok(())
+}
+
+fn test(options: &Options) -> io::Result<()> {
+    let start = Instant::now();
+    let mut input = Counter::new(open_input(&options.input)?);
+    // Decoding into a sink still verifies all checksums,
+    // but doesn't store any data
+    let mut sink = Counter::new(io::sink());
+    decode(options.format, &mut input, &mut sink)?;
+
+    let name = options.input.as_deref().unwrap_or("<stdin>");
+    match options.format {
+        // Raw deflate streams carry no checksum we could verify
+        Format::Deflate => eprintln!("{}: OK (stream is complete, raw deflate has no checksum)", name),
+        _ => eprintln!("{}: OK (checksum matches)", name),
+    }
+    report(sink.count, input.count, sink.count, start);
+    Ok(())
+}
+
+// Returns the gzip header of the first member, if there is one
+fn decode<R: Read, W: Write>(format: Format, input: &mut R, output: &mut W) -> io::Result<Option<GzHeader>> {
+    match format {
+        Format::Gzip => {
+            // MultiGzDecoder also handles files made of several
+            // concatenated gzip members, like `cat a.gz b.gz` produces
+            let mut decoder = MultiGzDecoder::new(input);
+            let header = decoder.header().cloned();
+            io::copy(&mut decoder, output)?;
+            if let Some(header) = &header {
+                print_gzip_header(header);
+            }
+            Ok(header)
+        }
+        Format::Zlib => io::copy(&mut ZlibDecoder::new(input), output).map(|_| None),
+        Format::Deflate => io::copy(&mut DeflateDecoder::new(input), output).map(|_| None),
+    }
+}
+
+// The name stored in the gzip header, as a file next to the compressed one.
+// The header comes from whoever made the file, so only a bare file name is used
+fn stored_name(path: &str) -> io::Result<Option<String>> {
+    let decoder = MultiGzDecoder::new(File::open(path)?);
+    let name = match decoder.header().and_then(GzHeader::filename) {
+        Some(name) => String::from_utf8_lossy(name).into_owned(),
+        None => return Ok(None),
+    };
+    // Drops any directories, and gives up on names like ".." or "/"
+    let name = match Path::new(&name).file_name() {
+        Some(name) => name,
+        None => return Ok(None),
+    };
+    let directory = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+    Ok(Some(directory.join(name).to_string_lossy().into_owned()))
+}
+
+fn print_gzip_header(header: &GzHeader) {
+    if let Some(name) = header.filename() {
+        eprintln!("Original name: {}", String::from_utf8_lossy(name));
+    }
+    if header.mtime() != 0 {
+        eprintln!("Original modification time: {} (seconds since epoch)", header.mtime());
+    }
+}
+
+// gzip headers only store the file name, not the whole path
+fn gzip_name(path: &str) -> Vec<u8> {
+    Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned().into_bytes())
+        .unwrap_or_default()
+}
+
+// gzip stores the modification time as 32 bit seconds since the epoch
+fn modification_time(path: &str) -> u32 {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_secs() as u32)
+        .unwrap_or(0)
+}
+
+fn report(uncompressed: u64, compressed: u64, processed: u64, start: Instant) {
+    let elapsed = start.elapsed().as_secs_f64();
+    let ratio = if uncompressed == 0 {
+        0.0
+    } else {
+        compressed as f64 / uncompressed as f64 * 100.0
+    };
+    let throughput = if elapsed > 0.0 {
+        processed as f64 / elapsed / (1024.0 * 1024.0)
+    } else {
+        0.0
+    };
+    eprintln!(
+        "{} bytes uncompressed, {} bytes compressed ({:.1}%), {:.3}s, {:.1} MiB/s",
+        uncompressed, compressed, ratio, elapsed, throughput
+    );
+}
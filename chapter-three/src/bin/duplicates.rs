@@ -0,0 +1,303 @@
+use rayon::prelude::*;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process;
+
+// How much of every file is hashed in the cheap second pass
+const PARTIAL_HASH_SIZE: u64 = 4096;
+
+fn main() {
+    let options = match Options::parse(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n", e);
+            eprintln!(
+                "Usage: duplicates [--json] [--min-size <bytes>] \
+                 [--hardlink | --delete] [--apply] <directory>..."
+            );
+            eprintln!("Actions only print what they would do unless --apply is given.");
+            process::exit(2);
+        }
+    };
+
+    let files = collect_files(&options.roots, options.min_size);
+    let duplicates = find_duplicates(files);
+
+    if options.json {
+        print_json(&duplicates);
+    } else {
+        print_text(&duplicates);
+    }
+
+    if let Some(action) = options.action {
+        if let Err(e) = apply(action, &duplicates, options.dry_run) {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Action {
+    Hardlink,
+    Delete,
+}
+
+struct Options {
+    roots: Vec<PathBuf>,
+    min_size: u64,
+    json: bool,
+    action: Option<Action>,
+    dry_run: bool,
+}
+
+impl Options {
+    fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+        let mut options = Options {
+            roots: Vec::new(),
+            // Empty files are all "equal", which is rarely interesting
+            min_size: 1,
+            json: false,
+            action: None,
+            dry_run: true,
+        };
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--json" => options.json = true,
+                "--min-size" => {
+                    let value = args.next().ok_or("Missing value for --min-size")?;
+                    options.min_size = value
+                        .parse()
+                        .map_err(|_| format!("Invalid size '{}'", value))?;
+                }
+                "--hardlink" | "--delete" if options.action.is_some() => {
+                    return Err("Only one of --hardlink and --delete can be given".to_string())
+                }
+                "--hardlink" => options.action = Some(Action::Hardlink),
+                "--delete" => options.action = Some(Action::Delete),
+                "--apply" => options.dry_run = false,
+                flag if flag.starts_with("--") => return Err(format!("Unknown option '{}'", flag)),
+                path => options.roots.push(PathBuf::from(path)),
+            }
+        }
+        if options.roots.is_empty() {
+            return Err("No directory given".to_string());
+        }
+        Ok(options)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Candidate {
+    path: PathBuf,
+    size: u64,
+}
+
+// A set of files that all have the same content
+struct DuplicateSet {
+    size: u64,
+    hash: String,
+    paths: Vec<PathBuf>,
+}
+
+fn collect_files(roots: &[PathBuf], min_size: u64) -> Vec<Candidate> {
+    let mut seen = HashSet::new();
+    roots
+        .iter()
+        .flat_map(|root| WalkDir::new(root).into_iter())
+        .filter_map(|entry| match entry {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                eprintln!("Skipping: {}", e);
+                None
+            }
+        })
+        // WalkDir doesn't follow symlinks by default,
+        // so we only ever see every real file once
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            // Hardlinks to the same file are not duplicates, they already share their data
+            if !seen.insert(file_id(&metadata, entry.path())) {
+                return None;
+            }
+            Some(Candidate {
+                path: entry.into_path(),
+                size: metadata.len(),
+            })
+        })
+        .filter(|candidate| candidate.size >= min_size)
+        .collect()
+}
+
+#[cfg(unix)]
+fn file_id(metadata: &fs::Metadata, _path: &Path) -> (u64, u64, PathBuf) {
+    use std::os::unix::fs::MetadataExt;
+    (metadata.dev(), metadata.ino(), PathBuf::new())
+}
+
+#[cfg(not(unix))]
+fn file_id(_metadata: &fs::Metadata, path: &Path) -> (u64, u64, PathBuf) {
+    (0, 0, path.to_path_buf())
+}
+
+fn find_duplicates(files: Vec<Candidate>) -> Vec<DuplicateSet> {
+    // Pass 1: files of different sizes can't be equal. This costs no reads at all
+    let by_size = group_by_size(files);
+
+    // Pass 2: most same-sized files already differ in their first block
+    let by_partial_hash: Vec<(String, Vec<Candidate>)> = by_size
+        .into_iter()
+        .flat_map(|group| group_by_hash(group, Some(PARTIAL_HASH_SIZE)))
+        .collect();
+
+    // Pass 3: only the remaining candidates get read in full
+    let mut duplicates: Vec<DuplicateSet> = by_partial_hash
+        .into_iter()
+        .flat_map(|(hash, group)| {
+            // Files no bigger than the first block were already hashed completely
+            if group[0].size <= PARTIAL_HASH_SIZE {
+                vec![(hash, group)]
+            } else {
+                group_by_hash(group, None)
+            }
+        })
+        .map(|(hash, group)| {
+            let mut paths: Vec<PathBuf> = group.iter().map(|candidate| candidate.path.clone()).collect();
+            paths.sort();
+            DuplicateSet {
+                size: group[0].size,
+                hash,
+                paths,
+            }
+        })
+        .collect();
+    // Show the sets that waste the most space first
+    duplicates.sort_by(|a, b| {
+        let wasted = |set: &DuplicateSet| set.size * (set.paths.len() as u64 - 1);
+        wasted(b).cmp(&wasted(a)).then_with(|| a.paths.cmp(&b.paths))
+    });
+    duplicates
+}
+
+// Split candidates into groups of the same size, dropping groups of one
+fn group_by_size(candidates: Vec<Candidate>) -> Vec<Vec<Candidate>> {
+    let mut groups: HashMap<u64, Vec<Candidate>> = HashMap::new();
+    for candidate in candidates {
+        groups.entry(candidate.size).or_default().push(candidate);
+    }
+    groups.into_values().filter(|group| group.len() > 1).collect()
+}
+
+fn group_by_hash(group: Vec<Candidate>, limit: Option<u64>) -> Vec<(String, Vec<Candidate>)> {
+    // Hashing is IO and CPU heavy, so every file is handled on its own thread
+    let hashed: Vec<(String, Candidate)> = group
+        .into_par_iter()
+        .filter_map(|candidate| match hash_file(&candidate.path, limit) {
+            Ok(hash) => Some((hash, candidate)),
+            Err(e) => {
+                eprintln!("Failed to read {}: {}", candidate.path.display(), e);
+                None
+            }
+        })
+        .collect();
+    let mut groups: HashMap<String, Vec<Candidate>> = HashMap::new();
+    for (hash, candidate) in hashed {
+        groups.entry(hash).or_default().push(candidate);
+    }
+    groups.into_iter().filter(|(_, group)| group.len() > 1).collect()
+}
+
+fn hash_file(path: &Path, limit: Option<u64>) -> io::Result<String> {
+    let file = BufReader::new(File::open(path)?);
+    let mut hasher = Sha256::new();
+    match limit {
+        Some(limit) => io::copy(&mut file.take(limit), &mut hasher)?,
+        None => io::copy(&mut { file }, &mut hasher)?,
+    };
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+fn print_text(duplicates: &[DuplicateSet]) {
+    let mut wasted = 0;
+    for set in duplicates {
+        println!("{} bytes x {} copies, sha256 {}", set.size, set.paths.len(), set.hash);
+        for path in &set.paths {
+            println!("  {}", path.display());
+        }
+        wasted += set.size * (set.paths.len() as u64 - 1);
+    }
+    println!(
+        "{} sets of duplicates, {} bytes could be saved",
+        duplicates.len(),
+        wasted
+    );
+}
+
+fn print_json(duplicates: &[DuplicateSet]) {
+    let sets: Vec<_> = duplicates
+        .iter()
+        .map(|set| {
+            json!({
+                "size": set.size,
+                "sha256": set.hash,
+                "paths": set.paths.iter().map(|path| path.to_string_lossy()).collect::<Vec<_>>(),
+            })
+        })
+        .collect();
+    let json = serde_json::to_string_pretty(&sets).expect("Failed to convert duplicates into JSON");
+    println!("{}", json);
+}
+
+// Keep the first path of every set and replace or remove the rest
+fn apply(action: Action, duplicates: &[DuplicateSet], dry_run: bool) -> io::Result<()> {
+    let prefix = if dry_run { "Would" } else { "Will" };
+    for set in duplicates {
+        let (original, copies) = set.paths.split_first().expect("Duplicate sets are never empty");
+        for copy in copies {
+            match action {
+                Action::Hardlink => {
+                    eprintln!("{} link {} to {}", prefix, copy.display(), original.display());
+                    if !dry_run {
+                        replace_with_hardlink(original, copy)?;
+                    }
+                }
+                Action::Delete => {
+                    eprintln!("{} delete {} (keeping {})", prefix, copy.display(), original.display());
+                    if !dry_run {
+                        fs::remove_file(copy)?;
+                    }
+                }
+            }
+        }
+    }
+    if dry_run {
+        eprintln!("This was a dry run, pass --apply to actually change files");
+    }
+    Ok(())
+}
+
+fn replace_with_hardlink(original: &Path, copy: &Path) -> io::Result<()> {
+    // Create the link next to the copy first and then rename it over the copy.
+    // This way, the copy is never missing, even if we crash halfway through
+    let mut temporary = copy.as_os_str().to_owned();
+    temporary.push(".duplicates-tmp");
+    let temporary = PathBuf::from(temporary);
+    fs::hard_link(original, &temporary)?;
+    if let Err(e) = fs::rename(&temporary, copy) {
+        let _ = fs::remove_file(&temporary);
+        return Err(e);
+    }
+    Ok(())
+}
@@ -0,0 +1,502 @@
+use std::env;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::process;
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if !args.is_empty() {
+        if let Err(e) = transcode_file(&args) {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
+
+    // Write the same text in a couple of legacy encodings
+    let text = "Größe: 5€ – “quoted” café\nSecond line\n";
+    let dir = env::temp_dir();
+    for (encoding, bom) in &[
+        (Encoding::Utf8, true),
+        (Encoding::Utf16Le, true),
+        (Encoding::Utf16Be, true),
+        (Encoding::Windows1252, false),
+    ] {
+        let path = dir.join(format!("encodings_{}.txt", encoding));
+        let mut bytes = if *bom { encoding.bom().to_vec() } else { Vec::new() };
+        bytes.extend(encode(text, *encoding).expect("Failed to encode text"));
+        fs::write(&path, &bytes).expect("Failed to write file");
+
+        // The BOM tells the reader what it's dealing with.
+        // Without one, we have to tell it what to expect
+        let file = File::open(&path).expect("Failed to open file");
+        let reader = DecodingReader::new(file, Some(*encoding).filter(|_| !bom), DecodeMode::Strict);
+        println!("{} ({} bytes):", encoding, bytes.len());
+        // Thanks to the adapter, lines() works like on any UTF-8 file
+        for line in BufReader::new(reader).lines() {
+            println!("  {}", line.expect("Failed to read line"));
+        }
+    }
+
+    // Latin-1 can't represent the euro sign
+    match encode(text, Encoding::Latin1) {
+        Ok(_) => println!("Encoded as Latin-1"),
+        Err(e) => println!("Latin-1: {}", e),
+    }
+
+    // A file that claims to be UTF-8 but contains a Windows-1252 byte
+    let broken = b"caf\xe9 au lait\nfine line\n";
+    let strict = decode(broken, Encoding::Utf8, DecodeMode::Strict);
+    println!("Strict decoding: {:?}", strict.map(|decoded| decoded.text).unwrap_err().to_string());
+    let lossy = decode(broken, Encoding::Utf8, DecodeMode::Lossy).expect("Lossy decoding never fails");
+    println!("Lossy decoding: {:?}", lossy.text);
+    let report = decode(broken, Encoding::Utf8, DecodeMode::Report).expect("Reporting never fails");
+    for error in &report.errors {
+        println!("Reported: {}", error);
+    }
+}
+
+// Usage: encodings <file> [--from <encoding>] [--to <encoding>] [--mode <strict|lossy|report>]
+fn transcode_file(args: &[String]) -> io::Result<()> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+    let mut path = None;
+    let mut from = None;
+    let mut to = Encoding::Utf8;
+    let mut mode = DecodeMode::Strict;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| invalid(format!("Missing value for {}", arg)));
+        match arg.as_str() {
+            "--from" => from = Some(value()?.parse().map_err(invalid)?),
+            "--to" => to = value()?.parse().map_err(invalid)?,
+            "--mode" => {
+                mode = match value()?.as_str() {
+                    "strict" => DecodeMode::Strict,
+                    "lossy" => DecodeMode::Lossy,
+                    "report" => DecodeMode::Report,
+                    other => return Err(invalid(format!("Unknown mode '{}'", other))),
+                }
+            }
+            _ => path = Some(arg.clone()),
+        }
+    }
+    let path = path.ok_or_else(|| invalid("No file given".to_string()))?;
+
+    let mut reader = DecodingReader::new(File::open(&path)?, from, mode);
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+    eprintln!("Decoded {} as {}", path, reader.encoding().map_or("unknown".to_string(), |e| e.to_string()));
+    for error in reader.errors() {
+        eprintln!("{}", error);
+    }
+    let bytes = encode(&text, to).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    io::stdout().write_all(&bytes)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+    Windows1252,
+}
+
+impl Encoding {
+    fn bom(self) -> &'static [u8] {
+        match self {
+            Encoding::Utf8 => b"\xEF\xBB\xBF",
+            Encoding::Utf16Le => b"\xFF\xFE",
+            Encoding::Utf16Be => b"\xFE\xFF",
+            Encoding::Latin1 | Encoding::Windows1252 => b"",
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Encoding::Utf8 => "utf-8",
+            Encoding::Utf16Le => "utf-16le",
+            Encoding::Utf16Be => "utf-16be",
+            Encoding::Latin1 => "latin-1",
+            Encoding::Windows1252 => "windows-1252",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl std::str::FromStr for Encoding {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Encoding, String> {
+        match name.to_lowercase().replace('_', "-").as_str() {
+            "utf-8" | "utf8" => Ok(Encoding::Utf8),
+            "utf-16le" | "utf16le" => Ok(Encoding::Utf16Le),
+            "utf-16be" | "utf16be" => Ok(Encoding::Utf16Be),
+            "latin-1" | "latin1" | "iso-8859-1" => Ok(Encoding::Latin1),
+            "windows-1252" | "cp1252" => Ok(Encoding::Windows1252),
+            _ => Err(format!("Unknown encoding '{}'", name)),
+        }
+    }
+}
+
+// Look for a byte order mark, returning the encoding and the length of the mark
+fn detect_bom(bytes: &[u8]) -> Option<(Encoding, usize)> {
+    [Encoding::Utf8, Encoding::Utf16Le, Encoding::Utf16Be]
+        .iter()
+        .find(|encoding| bytes.starts_with(encoding.bom()))
+        .map(|&encoding| (encoding, encoding.bom().len()))
+}
+
+// Windows-1252 is Latin-1 except for these printable characters in 0x80..=0x9F.
+// The five unassigned bytes decode to the C1 control with the same value,
+// just like browsers do
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '\u{20AC}', '\u{81}', '\u{201A}', '\u{192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{2C6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8D}', '\u{17D}', '\u{8F}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{2DC}', '\u{2122}', '\u{161}', '\u{203A}', '\u{153}', '\u{9D}', '\u{17E}', '\u{178}',
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DecodeMode {
+    // Fail on the first invalid sequence
+    Strict,
+    // Replace invalid sequences with U+FFFD
+    Lossy,
+    // Like lossy, but remember where every invalid sequence was
+    Report,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct DecodeError {
+    // Position in the input, counted from the very first byte (including any BOM)
+    offset: u64,
+    bytes: Vec<u8>,
+    encoding: Encoding,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Invalid {} sequence {:02X?} at byte offset {}",
+            self.encoding, self.bytes, self.offset
+        )
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+#[derive(Debug, Clone, PartialEq)]
+struct EncodeError {
+    character: char,
+    // Position in the text in chars
+    index: usize,
+    encoding: Encoding,
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Character {:?} at index {} can't be represented in {}",
+            self.character, self.index, self.encoding
+        )
+    }
+}
+
+impl std::error::Error for EncodeError {}
+
+struct Decoded {
+    text: String,
+    errors: Vec<DecodeError>,
+}
+
+// Decode a complete buffer. A BOM at the start overrides the given encoding
+fn decode(bytes: &[u8], encoding: Encoding, mode: DecodeMode) -> Result<Decoded, DecodeError> {
+    let mut decoder = Decoder::new(encoding, mode);
+    let mut text = String::new();
+    let bytes = match detect_bom(bytes) {
+        Some((detected, len)) => {
+            decoder = Decoder::new(detected, mode);
+            decoder.offset = len as u64;
+            &bytes[len..]
+        }
+        None => bytes,
+    };
+    decoder.decode(bytes, true, &mut text)?;
+    Ok(Decoded {
+        text,
+        errors: decoder.errors,
+    })
+}
+
+fn encode(text: &str, encoding: Encoding) -> Result<Vec<u8>, EncodeError> {
+    let unrepresentable = |character, index| EncodeError {
+        character,
+        index,
+        encoding,
+    };
+    match encoding {
+        Encoding::Utf8 => Ok(text.as_bytes().to_vec()),
+        Encoding::Utf16Le => Ok(text.encode_utf16().flat_map(u16::to_le_bytes).collect()),
+        Encoding::Utf16Be => Ok(text.encode_utf16().flat_map(u16::to_be_bytes).collect()),
+        Encoding::Latin1 => text
+            .chars()
+            .enumerate()
+            .map(|(index, c)| u8::try_from(u32::from(c)).map_err(|_| unrepresentable(c, index)))
+            .collect(),
+        Encoding::Windows1252 => text
+            .chars()
+            .enumerate()
+            .map(|(index, c)| match WINDOWS_1252_HIGH.iter().position(|&high| high == c) {
+                Some(position) => Ok(0x80 + position as u8),
+                None => match u32::from(c) {
+                    // The C1 range is taken by the table above
+                    0x80..=0x9F => Err(unrepresentable(c, index)),
+                    code => u8::try_from(code).map_err(|_| unrepresentable(c, index)),
+                },
+            })
+            .collect(),
+    }
+}
+
+// Decodes input that arrives in chunks, keeping sequences
+// that are split between two chunks until the rest arrives
+struct Decoder {
+    encoding: Encoding,
+    mode: DecodeMode,
+    // Bytes of an incomplete sequence at the end of the last chunk
+    pending: Vec<u8>,
+    // How many bytes came before `pending`
+    offset: u64,
+    errors: Vec<DecodeError>,
+}
+
+impl Decoder {
+    fn new(encoding: Encoding, mode: DecodeMode) -> Decoder {
+        Decoder {
+            encoding,
+            mode,
+            pending: Vec::new(),
+            offset: 0,
+            errors: Vec::new(),
+        }
+    }
+
+    fn decode(&mut self, input: &[u8], last: bool, output: &mut String) -> Result<(), DecodeError> {
+        let mut bytes = std::mem::take(&mut self.pending);
+        bytes.extend_from_slice(input);
+        let consumed = match self.encoding {
+            Encoding::Utf8 => self.decode_utf8(&bytes, last, output)?,
+            Encoding::Utf16Le => self.decode_utf16(&bytes, last, output, u16::from_le_bytes)?,
+            Encoding::Utf16Be => self.decode_utf16(&bytes, last, output, u16::from_be_bytes)?,
+            // Every byte is a complete character in single-byte encodings
+            Encoding::Latin1 => {
+                output.extend(bytes.iter().map(|&byte| char::from(byte)));
+                bytes.len()
+            }
+            Encoding::Windows1252 => {
+                output.extend(bytes.iter().map(|&byte| match byte {
+                    0x80..=0x9F => WINDOWS_1252_HIGH[(byte - 0x80) as usize],
+                    _ => char::from(byte),
+                }));
+                bytes.len()
+            }
+        };
+        self.offset += consumed as u64;
+        self.pending = bytes[consumed..].to_vec();
+        Ok(())
+    }
+
+    // Deal with an invalid sequence according to the mode
+    fn invalid(&mut self, position: usize, bytes: &[u8], output: &mut String) -> Result<(), DecodeError> {
+        let error = DecodeError {
+            offset: self.offset + position as u64,
+            bytes: bytes.to_vec(),
+            encoding: self.encoding,
+        };
+        match self.mode {
+            DecodeMode::Strict => return Err(error),
+            DecodeMode::Lossy => {}
+            DecodeMode::Report => self.errors.push(error),
+        }
+        output.push(char::REPLACEMENT_CHARACTER);
+        Ok(())
+    }
+
+    // Returns how many bytes were consumed
+    fn decode_utf8(&mut self, bytes: &[u8], last: bool, output: &mut String) -> Result<usize, DecodeError> {
+        let mut position = 0;
+        loop {
+            match std::str::from_utf8(&bytes[position..]) {
+                Ok(valid) => {
+                    output.push_str(valid);
+                    return Ok(bytes.len());
+                }
+                Err(e) => {
+                    let valid_end = position + e.valid_up_to();
+                    output.push_str(std::str::from_utf8(&bytes[position..valid_end]).expect("Checked above"));
+                    match e.error_len() {
+                        Some(len) => {
+                            self.invalid(valid_end, &bytes[valid_end..valid_end + len], output)?;
+                            position = valid_end + len;
+                        }
+                        // The sequence is cut off by the end of the chunk
+                        None if !last => return Ok(valid_end),
+                        None => {
+                            self.invalid(valid_end, &bytes[valid_end..], output)?;
+                            return Ok(bytes.len());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn decode_utf16(
+        &mut self,
+        bytes: &[u8],
+        last: bool,
+        output: &mut String,
+        to_unit: fn([u8; 2]) -> u16,
+    ) -> Result<usize, DecodeError> {
+        let unit_at = |position: usize| to_unit([bytes[position], bytes[position + 1]]);
+        let mut position = 0;
+        while position + 2 <= bytes.len() {
+            let unit = unit_at(position);
+            match unit {
+                0xD800..=0xDBFF => {
+                    // A high surrogate needs a low surrogate right after it
+                    if position + 4 > bytes.len() {
+                        if !last {
+                            return Ok(position);
+                        }
+                        self.invalid(position, &bytes[position..], output)?;
+                        return Ok(bytes.len());
+                    }
+                    let next = unit_at(position + 2);
+                    if (0xDC00..=0xDFFF).contains(&next) {
+                        let code = 0x10000 + ((u32::from(unit) - 0xD800) << 10) + (u32::from(next) - 0xDC00);
+                        output.push(char::from_u32(code).expect("Surrogate pairs are always valid"));
+                        position += 4;
+                    } else {
+                        self.invalid(position, &bytes[position..position + 2], output)?;
+                        position += 2;
+                    }
+                }
+                0xDC00..=0xDFFF => {
+                    self.invalid(position, &bytes[position..position + 2], output)?;
+                    position += 2;
+                }
+                _ => {
+                    output.push(char::from_u32(u32::from(unit)).expect("Non-surrogates are always valid"));
+                    position += 2;
+                }
+            }
+        }
+        // A single byte is left over
+        if position < bytes.len() && last {
+            self.invalid(position, &bytes[position..], output)?;
+            return Ok(bytes.len());
+        }
+        Ok(position)
+    }
+}
+
+// Wraps any reader of encoded text and turns it into a reader of UTF-8,
+// so everything that works on UTF-8 readers works on legacy files too
+struct DecodingReader<R> {
+    inner: R,
+    // Used when the input doesn't start with a BOM
+    fallback: Encoding,
+    // Created once the first chunk told us about a BOM
+    decoder: Option<Decoder>,
+    mode: DecodeMode,
+    // Decoded text that wasn't read yet
+    buffer: Vec<u8>,
+    position: usize,
+    done: bool,
+}
+
+impl<R: Read> DecodingReader<R> {
+    // A BOM always wins, without one the given encoding or UTF-8 is used
+    fn new(inner: R, encoding: Option<Encoding>, mode: DecodeMode) -> DecodingReader<R> {
+        DecodingReader {
+            inner,
+            fallback: encoding.unwrap_or(Encoding::Utf8),
+            decoder: None,
+            mode,
+            buffer: Vec::new(),
+            position: 0,
+            done: false,
+        }
+    }
+
+    fn encoding(&self) -> Option<Encoding> {
+        self.decoder.as_ref().map(|decoder| decoder.encoding)
+    }
+
+    fn errors(&self) -> &[DecodeError] {
+        self.decoder.as_ref().map_or(&[], |decoder| &decoder.errors)
+    }
+
+    // Read and decode the next chunk of input
+    fn fill(&mut self) -> io::Result<()> {
+        let mut chunk = vec![0; 8 * 1024];
+        let mut read = self.inner.read(&mut chunk)?;
+        // Only the input running dry means we're done, a chunk
+        // that held nothing but the BOM doesn't
+        let last = read == 0;
+
+        let decoder = match &mut self.decoder {
+            Some(decoder) => decoder,
+            None => {
+                // Make sure we've seen enough bytes to recognize any BOM
+                while read < 3 {
+                    let more = self.inner.read(&mut chunk[read..])?;
+                    if more == 0 {
+                        break;
+                    }
+                    read += more;
+                }
+                let (encoding, skip) = detect_bom(&chunk[..read]).unwrap_or((self.fallback, 0));
+                let mut decoder = Decoder::new(encoding, self.mode);
+                decoder.offset = skip as u64;
+                chunk.drain(..skip);
+                read -= skip;
+                self.decoder.get_or_insert(decoder)
+            }
+        };
+
+        let mut text = String::new();
+        decoder
+            .decode(&chunk[..read], last, &mut text)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        self.buffer = text.into_bytes();
+        self.position = 0;
+        self.done = last;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for DecodingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // A chunk can decode to nothing, e.g. when it only holds
+        // half a character, so keep going until there's output
+        while self.position == self.buffer.len() {
+            if self.done {
+                return Ok(0);
+            }
+            self.fill()?;
+        }
+        let available = &self.buffer[self.position..];
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.position += len;
+        Ok(len)
+    }
+}
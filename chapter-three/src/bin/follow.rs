@@ -0,0 +1,268 @@
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::Duration;
+
+fn main() {
+    let options = match Options::parse(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n", e);
+            eprintln!(
+                "Usage: follow [file] [-n <lines>] [--state <file>] [--interval <ms>]\n\
+                 \n\
+                 Follows ../chapter-six/log.txt, the file written by custom_logger, by default.\n\
+                 With --state, the position is saved after every line and picked up again on restart."
+            );
+            process::exit(2);
+        }
+    };
+
+    let follower = Follower::open(&options.path, options.last_lines, options.state, options.interval);
+    let follower = match follower {
+        Ok(follower) => follower,
+        Err(e) => {
+            eprintln!("Failed to open {}: {}", options.path.display(), e);
+            process::exit(1);
+        }
+    };
+    for line in follower {
+        match line {
+            Ok(line) => println!("{}", line),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                process::exit(1);
+            }
+        }
+    }
+}
+
+struct Options {
+    path: PathBuf,
+    // How many existing lines to show when there is no saved position
+    last_lines: usize,
+    state: Option<PathBuf>,
+    interval: Duration,
+}
+
+impl Options {
+    fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+        let mut options = Options {
+            path: PathBuf::from("../chapter-six/log.txt"),
+            last_lines: 10,
+            state: None,
+            interval: Duration::from_millis(250),
+        };
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-n" => {
+                    let value = args.next().ok_or("Missing value for -n")?;
+                    let lines = value.parse().map_err(|_| format!("Invalid line count '{}'", value))?;
+                    options.last_lines = lines;
+                }
+                "--state" => options.state = Some(PathBuf::from(args.next().ok_or("Missing value for --state")?)),
+                "--interval" => {
+                    let value = args.next().ok_or("Missing value for --interval")?;
+                    let millis = value.parse().map_err(|_| format!("Invalid interval '{}'", value))?;
+                    options.interval = Duration::from_millis(millis);
+                }
+                flag if flag.starts_with('-') => return Err(format!("Unknown option '{}'", flag)),
+                path => options.path = PathBuf::from(path),
+            }
+        }
+        Ok(options)
+    }
+}
+
+// Where we stopped reading, identified by the file's inode
+// so a rotated file isn't mistaken for the old one
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Position {
+    inode: u64,
+    offset: u64,
+}
+
+impl Position {
+    fn load(path: &Path) -> Option<Position> {
+        let content = fs::read_to_string(path).ok()?;
+        let mut parts = content.split_whitespace();
+        Some(Position {
+            inode: parts.next()?.parse().ok()?,
+            offset: parts.next()?.parse().ok()?,
+        })
+    }
+
+    fn save(&self, path: &Path) -> io::Result<()> {
+        // Write next to the real file and rename, so a crash
+        // never leaves a half-written position behind
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(".tmp");
+        fs::write(&temporary, format!("{} {}\n", self.inode, self.offset))?;
+        fs::rename(temporary, path)
+    }
+}
+
+// Yields every line appended to a file, waiting for more when it reaches the end
+struct Follower {
+    path: PathBuf,
+    reader: BufReader<File>,
+    inode: u64,
+    offset: u64,
+    // A line the writer hasn't finished yet. Kept as bytes, since
+    // the writer may have stopped in the middle of a character
+    partial: Vec<u8>,
+    state: Option<PathBuf>,
+    interval: Duration,
+}
+
+impl Follower {
+    fn open(path: &Path, last_lines: usize, state: Option<PathBuf>, interval: Duration) -> io::Result<Follower> {
+        let file = File::open(path)?;
+        let inode = inode_of(&file.metadata()?);
+        let len = file.metadata()?.len();
+
+        // Resume where we left off, but only if it's still the same file
+        // and it hasn't been truncated in the meantime
+        let saved = state.as_deref().and_then(Position::load);
+        let offset = match saved {
+            Some(position) if position.inode == inode && position.offset <= len => position.offset,
+            _ => offset_of_last_lines(&file, last_lines)?,
+        };
+
+        let mut reader = BufReader::new(file);
+        reader.seek(SeekFrom::Start(offset))?;
+        Ok(Follower {
+            path: path.to_path_buf(),
+            reader,
+            inode,
+            offset,
+            partial: Vec::new(),
+            state,
+            interval,
+        })
+    }
+
+    // Read a complete line if one is available, without waiting
+    fn read_line(&mut self) -> io::Result<Option<String>> {
+        let read = self.reader.read_until(b'\n', &mut self.partial)?;
+        self.offset += read as u64;
+        if self.partial.ends_with(b"\n") {
+            let mut bytes = std::mem::take(&mut self.partial);
+            bytes.pop();
+            if bytes.ends_with(b"\r") {
+                bytes.pop();
+            }
+            // A log with a stray Latin-1 byte is still worth following
+            let line = String::from_utf8_lossy(&bytes).into_owned();
+            if let Some(state) = &self.state {
+                let position = Position {
+                    inode: self.inode,
+                    offset: self.offset,
+                };
+                position.save(state)?;
+            }
+            return Ok(Some(line));
+        }
+        Ok(None)
+    }
+
+    // Called whenever we reached the end of the file
+    fn check_for_rotation(&mut self) -> io::Result<()> {
+        let metadata = match fs::metadata(&self.path) {
+            Ok(metadata) => metadata,
+            // During a rotation, the file can be missing for a moment
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        if inode_of(&metadata) != self.inode {
+            // A new file took the place of ours, but the old one may have
+            // gotten more lines right before that. Those come first,
+            // we only switch over once there's nothing left in it
+            if !self.reader.fill_buf()?.is_empty() {
+                return Ok(());
+            }
+            eprintln!("{} was rotated, reopening it", self.path.display());
+            self.reopen()?;
+        } else if metadata.len() < self.offset {
+            eprintln!("{} was truncated, reading from the start", self.path.display());
+            self.partial.clear();
+            self.reader.seek(SeekFrom::Start(0))?;
+            self.offset = 0;
+        }
+        Ok(())
+    }
+
+    fn reopen(&mut self) -> io::Result<()> {
+        let file = File::open(&self.path)?;
+        self.inode = inode_of(&file.metadata()?);
+        self.reader = BufReader::new(file);
+        self.offset = 0;
+        self.partial.clear();
+        Ok(())
+    }
+}
+
+impl Iterator for Follower {
+    type Item = io::Result<String>;
+
+    // This iterator never ends on its own, it waits for new lines forever
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.read_line() {
+                Ok(Some(line)) => return Some(Ok(line)),
+                Ok(None) => {}
+                Err(e) => return Some(Err(e)),
+            }
+            if let Err(e) = self.check_for_rotation() {
+                return Some(Err(e));
+            }
+            thread::sleep(self.interval);
+        }
+    }
+}
+
+#[cfg(unix)]
+fn inode_of(metadata: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.ino()
+}
+
+#[cfg(not(unix))]
+fn inode_of(_metadata: &fs::Metadata) -> u64 {
+    // Without inodes, rotation shows up as truncation instead
+    0
+}
+
+// Find where the last `lines` lines of a file begin by reading it backwards in blocks
+fn offset_of_last_lines(mut file: &File, lines: usize) -> io::Result<u64> {
+    const BLOCK_SIZE: u64 = 4096;
+    let len = file.metadata()?.len();
+    if lines == 0 {
+        return Ok(len);
+    }
+    let mut end = len;
+    let mut newlines = 0;
+    let mut block = vec![0; BLOCK_SIZE as usize];
+    while end > 0 {
+        let start = end.saturating_sub(BLOCK_SIZE);
+        let block = &mut block[..(end - start) as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(block)?;
+        for (index, &byte) in block.iter().enumerate().rev() {
+            let position = start + index as u64;
+            // The newline ending the very last line doesn't start a new one
+            if byte == b'\n' && position + 1 != len {
+                newlines += 1;
+                if newlines == lines {
+                    return Ok(position + 1);
+                }
+            }
+        }
+        end = start;
+    }
+    // The file has fewer lines than we asked for
+    Ok(0)
+}
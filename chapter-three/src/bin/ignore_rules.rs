@@ -0,0 +1,399 @@
+use walkdir::{DirEntry, WalkDir};
+
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+
+const IGNORE_FILE_NAME: &str = ".gitignore";
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let root = PathBuf::from(args.next().unwrap_or_else(|| ".".to_string()));
+    let explain: Vec<String> = match args.next().as_deref() {
+        Some("--explain") => args.collect(),
+        Some(other) => {
+            eprintln!("Usage: ignore_rules [root] [--explain <path>...]");
+            eprintln!("Unknown argument '{}'", other);
+            std::process::exit(2);
+        }
+        None => Vec::new(),
+    };
+
+    let mut ignore = Ignore::new(&root);
+
+    if !explain.is_empty() {
+        for path in explain {
+            let path = Path::new(&path);
+            let is_dir = root.join(path).is_dir();
+            match ignore.check(path, is_dir) {
+                Some(found) => println!("{}: {}", path.display(), found),
+                None => println!("{}: not matched by any rule", path.display()),
+            }
+        }
+        return;
+    }
+
+    println!("All files under {} that are not ignored:", root.display());
+    WalkDir::new(&root)
+        .min_depth(1)
+        .sort_by_file_name()
+        .into_iter()
+        // Skipping an ignored directory skips its whole subtree,
+        // which is exactly how git treats excluded directories
+        .filter_entry(|entry| !ignore.is_ignored_entry(&root, entry))
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .for_each(|entry| println!("{}", entry.path().display()));
+}
+
+// A single line of an ignore file
+#[derive(Debug)]
+struct Rule {
+    pattern: Vec<Token>,
+    negated: bool,
+    directory_only: bool,
+    // Anchored rules match against the full path relative to the
+    // ignore file, the others against the name of the entry only
+    anchored: bool,
+    source: Rc<Path>,
+    line: usize,
+    text: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Char(char),
+    // * matches anything except a slash
+    Star,
+    // ? matches one character except a slash
+    Question,
+    // [a-z] or [!a-z]
+    Class { negated: bool, ranges: Vec<(char, char)> },
+    // ** as a whole path component, matches across slashes
+    DoubleStar,
+    // **/ matches zero or more leading directories
+    DoubleStarSlash,
+}
+
+// The answer to "why is this path (not) ignored?"
+struct Match<'a> {
+    rule: &'a Rule,
+    // Set when the rule excluded a parent directory instead of the path itself
+    parent: Option<String>,
+}
+
+impl Match<'_> {
+    fn is_ignored(&self) -> bool {
+        !self.rule.negated
+    }
+}
+
+impl fmt::Display for Match<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} by rule '{}' at {}:{}",
+            if self.is_ignored() { "ignored" } else { "re-included" },
+            self.rule.text,
+            self.rule.source.display(),
+            self.rule.line
+        )?;
+        if let Some(parent) = &self.parent {
+            write!(f, ", which excludes its parent directory {}", parent)?;
+        }
+        Ok(())
+    }
+}
+
+// The parsed contents of one ignore file
+struct IgnoreFile {
+    rules: Vec<Rule>,
+}
+
+impl IgnoreFile {
+    fn from_file(path: &Path) -> io::Result<IgnoreFile> {
+        let content = fs::read_to_string(path)?;
+        Ok(IgnoreFile::parse(&content, path))
+    }
+
+    fn parse(content: &str, source: &Path) -> IgnoreFile {
+        let source: Rc<Path> = Rc::from(source);
+        let rules = content
+            .lines()
+            .enumerate()
+            .filter_map(|(index, line)| parse_rule(line, Rc::clone(&source), index + 1))
+            .collect();
+        IgnoreFile { rules }
+    }
+
+    // Within one file, the last matching rule wins
+    fn matching_rule(&self, relative: &str, is_dir: bool) -> Option<&Rule> {
+        self.rules.iter().rev().find(|rule| rule.matches(relative, is_dir))
+    }
+}
+
+fn parse_rule(line: &str, source: Rc<Path>, line_number: usize) -> Option<Rule> {
+    // Comments and blank lines carry no rules
+    if line.starts_with('#') || line.trim().is_empty() {
+        return None;
+    }
+    let text = line.to_string();
+    let mut line = trim_unescaped_trailing_spaces(line);
+
+    let negated = line.starts_with('!');
+    if negated {
+        line = &line[1..];
+    }
+    let directory_only = line.ends_with('/') && !line.ends_with("\\/");
+    if directory_only {
+        line = &line[..line.len() - 1];
+    }
+    // A slash at the beginning or in the middle anchors the rule
+    // to the directory containing the ignore file
+    let anchored = line.contains('/');
+    let line = line.strip_prefix('/').unwrap_or(line);
+    if line.is_empty() {
+        return None;
+    }
+
+    Some(Rule {
+        pattern: tokenize(line),
+        negated,
+        directory_only,
+        anchored,
+        source,
+        line: line_number,
+        text,
+    })
+}
+
+fn trim_unescaped_trailing_spaces(line: &str) -> &str {
+    let mut end = line.len();
+    while line[..end].ends_with(' ') {
+        // "foo\ " keeps its trailing space
+        let backslashes = line[..end - 1].chars().rev().take_while(|&c| c == '\\').count();
+        if backslashes % 2 == 1 {
+            break;
+        }
+        end -= 1;
+    }
+    &line[..end]
+}
+
+fn tokenize(pattern: &str) -> Vec<Token> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let at_component_start = i == 0 || chars[i - 1] == '/';
+        match chars[i] {
+            '\\' if i + 1 < chars.len() => {
+                tokens.push(Token::Char(chars[i + 1]));
+                i += 2;
+                continue;
+            }
+            '*' if chars.get(i + 1) == Some(&'*') && at_component_start => {
+                match chars.get(i + 2) {
+                    Some('/') => {
+                        tokens.push(Token::DoubleStarSlash);
+                        i += 3;
+                        continue;
+                    }
+                    None => {
+                        tokens.push(Token::DoubleStar);
+                        i += 2;
+                        continue;
+                    }
+                    // Something like **foo is just two regular stars
+                    _ => tokens.push(Token::Star),
+                }
+            }
+            '*' => tokens.push(Token::Star),
+            '?' => tokens.push(Token::Question),
+            '[' => {
+                if let Some((class, length)) = parse_class(&chars[i..]) {
+                    tokens.push(class);
+                    i += length;
+                    continue;
+                }
+                // An unclosed bracket is taken literally
+                tokens.push(Token::Char('['));
+            }
+            c => tokens.push(Token::Char(c)),
+        }
+        i += 1;
+    }
+    // Collapse runs of stars, they mean the same as a single one
+    tokens.dedup_by(|a, b| *a == Token::Star && *b == Token::Star);
+    tokens
+}
+
+// Parse "[...]" at the start of chars, returning the class and its length
+fn parse_class(chars: &[char]) -> Option<(Token, usize)> {
+    let mut i = 1;
+    let negated = matches!(chars.get(i), Some('!') | Some('^'));
+    if negated {
+        i += 1;
+    }
+    let mut ranges = Vec::new();
+    let mut first = true;
+    loop {
+        let mut c = *chars.get(i)?;
+        // A ']' right at the start is part of the class
+        if c == ']' && !first {
+            return Some((Token::Class { negated, ranges }, i + 1));
+        }
+        if c == '\\' {
+            i += 1;
+            c = *chars.get(i)?;
+        }
+        first = false;
+        if chars.get(i + 1) == Some(&'-') && chars.get(i + 2).is_some_and(|&end| end != ']') {
+            let mut end = chars[i + 2];
+            i += 2;
+            if end == '\\' {
+                i += 1;
+                end = *chars.get(i)?;
+            }
+            ranges.push((c, end));
+        } else {
+            ranges.push((c, c));
+        }
+        i += 1;
+    }
+}
+
+impl Rule {
+    fn matches(&self, relative: &str, is_dir: bool) -> bool {
+        if self.directory_only && !is_dir {
+            return false;
+        }
+        let text: Vec<char> = if self.anchored {
+            relative.chars().collect()
+        } else {
+            let name = relative.rsplit('/').next().unwrap_or(relative);
+            name.chars().collect()
+        };
+        wildmatch(&self.pattern, &text)
+    }
+}
+
+fn wildmatch(pattern: &[Token], text: &[char]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((token, rest)) => match token {
+            Token::Char(c) => text.first() == Some(c) && wildmatch(rest, &text[1..]),
+            Token::Question => {
+                text.first().is_some_and(|&c| c != '/') && wildmatch(rest, &text[1..])
+            }
+            Token::Class { negated, ranges } => text.first().is_some_and(|&c| {
+                c != '/'
+                    && ranges.iter().any(|&(start, end)| start <= c && c <= end) != *negated
+                    && wildmatch(rest, &text[1..])
+            }),
+            Token::Star => {
+                // Try every length that doesn't cross a directory boundary
+                let limit = text.iter().position(|&c| c == '/').unwrap_or(text.len());
+                (0..=limit).any(|skip| wildmatch(rest, &text[skip..]))
+            }
+            Token::DoubleStar => (0..=text.len()).any(|skip| wildmatch(rest, &text[skip..])),
+            Token::DoubleStarSlash => {
+                // Either no directory at all, or skip up to and including a slash
+                wildmatch(rest, text)
+                    || text
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, &c)| c == '/')
+                        .any(|(index, _)| wildmatch(rest, &text[index + 1..]))
+            }
+        },
+    }
+}
+
+// All ignore files below a root directory, loaded as the walk discovers them
+struct Ignore {
+    root: PathBuf,
+    // Keyed by the directory relative to the root, "" is the root itself
+    files: HashMap<String, Option<IgnoreFile>>,
+}
+
+impl Ignore {
+    fn new(root: &Path) -> Ignore {
+        Ignore {
+            root: root.to_path_buf(),
+            files: HashMap::new(),
+        }
+    }
+
+    fn is_ignored_entry(&mut self, root: &Path, entry: &DirEntry) -> bool {
+        // The repository's own metadata is never part of the tree
+        if entry.file_type().is_dir() && entry.file_name() == ".git" {
+            return true;
+        }
+        let relative = entry.path().strip_prefix(root).unwrap_or(entry.path());
+        self.check(relative, entry.file_type().is_dir())
+            .is_some_and(|found| found.is_ignored())
+    }
+
+    // Find the rule that decides over a path relative to the root, if any.
+    // Like in git, nothing inside an excluded directory can be re-included,
+    // so the parent directories are checked first.
+    fn check(&mut self, relative: &Path, is_dir: bool) -> Option<Match<'_>> {
+        // "./src/main.rs" is the same path as "src/main.rs"
+        let parts: Vec<String> = relative
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(part) => Some(part.to_string_lossy().into_owned()),
+                _ => None,
+            })
+            .collect();
+        for depth in 0..parts.len() {
+            self.load(&parts[..depth].join("/"));
+        }
+
+        for depth in 1..parts.len() {
+            if let Some(rule) = self.matching_rule(&parts[..depth], true) {
+                if !rule.negated {
+                    return Some(Match {
+                        rule,
+                        parent: Some(parts[..depth].join("/")),
+                    });
+                }
+            }
+        }
+        self.matching_rule(&parts, is_dir)
+            .map(|rule| Match { rule, parent: None })
+    }
+
+    // Ignore files deeper in the tree take precedence over higher ones
+    fn matching_rule(&self, parts: &[String], is_dir: bool) -> Option<&Rule> {
+        let path = parts.join("/");
+        (0..parts.len()).rev().find_map(|depth| {
+            let directory = parts[..depth].join("/");
+            let file = self.files.get(&directory)?.as_ref()?;
+            // Rules match against the path relative to their own file
+            let start = if directory.is_empty() { 0 } else { directory.len() + 1 };
+            file.matching_rule(&path[start..], is_dir)
+        })
+    }
+
+    fn load(&mut self, directory: &str) {
+        if self.files.contains_key(directory) {
+            return;
+        }
+        let path = self.root.join(directory).join(IGNORE_FILE_NAME);
+        let file = match IgnoreFile::from_file(&path) {
+            Ok(file) => Some(file),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => {
+                eprintln!("Failed to read {}: {}", path.display(), e);
+                None
+            }
+        };
+        self.files.insert(directory.to_string(), file);
+    }
+}
@@ -0,0 +1,265 @@
+use byteorder::{ByteOrder, ReadBytesExt, BE, LE};
+use memmap2::Mmap;
+
+use std::env;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::Path;
+
+fn main() {
+    // bar.bin is the file written by the binary_files recipe
+    let path = env::args().nth(1).unwrap_or_else(|| "./bar.bin".to_string());
+    let mut file = MappedFile::open(&path).expect("Failed to open file");
+    let len = match file.len().expect("Failed to get file length") {
+        Some(len) => format!("{} bytes long", len),
+        None => "of unknown length".to_string(),
+    };
+    println!(
+        "{} is {} and {}",
+        path,
+        len,
+        if file.is_mapped() { "memory mapped" } else { "read through a buffer" }
+    );
+
+    let payload = read_protocol(&mut file.reader().expect("Failed to rewind file")).expect("Failed to parse file");
+    print!("The protocol contained the following payload: ");
+    for num in payload {
+        print!("0x{:X} ", num);
+    }
+    println!();
+
+    // Slices of a mapped file are bounds checked and never copied
+    if let Some(bytes) = file.as_bytes() {
+        match slice(bytes, 0..10) {
+            Ok(magic) => println!("The first ten bytes are {:?}", String::from_utf8_lossy(magic)),
+            Err(e) => println!("Failed to slice: {}", e),
+        }
+        if let Err(e) = slice(bytes, 0..bytes.len() + 1) {
+            println!("Slicing past the end fails cleanly: {}", e);
+        }
+    }
+
+    // The cursor reads any number type in either byte order
+    let data = [2, 3, 12, 8, 5, 0, 0, 0, 0xCD, 0xCC, 0x05, 0xC2, 0, 0, 0, 0, 0, 0, 0xF0, 0x3F];
+    let mut cursor = ByteCursor::new(&data);
+    println!("first byte: {}", cursor.read_u8().expect("Failed to read byte"));
+    cursor.seek(SeekFrom::Start(0)).expect("Failed to seek");
+    println!("u16 in little endian: {}", cursor.read_u16::<LE>().expect("Failed to read u16"));
+    cursor.seek(SeekFrom::Start(0)).expect("Failed to seek");
+    println!("u16 in big endian: {}", cursor.read_u16::<BE>().expect("Failed to read u16"));
+    cursor.seek(SeekFrom::Start(4)).expect("Failed to seek");
+    println!("i32 in little endian: {}", cursor.read_i32::<LE>().expect("Failed to read i32"));
+    println!("f32 in little endian: {}", cursor.read_f32::<LE>().expect("Failed to read f32"));
+    println!("f64 in little endian: {}", cursor.read_f64::<LE>().expect("Failed to read f64"));
+    cursor.seek(SeekFrom::Start(0)).expect("Failed to seek");
+    println!("u64 in big endian: {}", cursor.read_u64::<BE>().expect("Failed to read u64"));
+    // Reading past the end is an error, not a panic
+    cursor.seek(SeekFrom::End(-2)).expect("Failed to seek");
+    if let Err(e) = cursor.read_u32::<LE>() {
+        println!("Reading a u32 from the last two bytes fails: {}", e);
+    }
+}
+
+// The same protocol as in binary_files, parsed without a single copy
+// Works the same on a mapped file and on a buffered one
+fn read_protocol<R: BufRead>(reader: &mut R) -> io::Result<Vec<u32>> {
+    let mut magic = [0; 10];
+    reader.read_exact(&mut magic)?;
+    if &magic != b"MyProtocol" {
+        return Err(io::Error::other(
+            "Protocol didn't start with the expected magic string",
+        ));
+    }
+    let mut endianness = [0; 2];
+    reader.read_exact(&mut endianness)?;
+    match &endianness {
+        b"LE" => read_payload::<LE, _>(reader),
+        b"BE" => read_payload::<BE, _>(reader),
+        _ => Err(io::Error::other("Failed to parse endianness")),
+    }
+}
+
+fn read_payload<E: ByteOrder, R: BufRead>(reader: &mut R) -> io::Result<Vec<u32>> {
+    let mut payload = Vec::new();
+    // A clean end of the file can only come between two numbers
+    while !reader.fill_buf()?.is_empty() {
+        payload.push(reader.read_u32::<E>().map_err(|_| {
+            io::Error::new(io::ErrorKind::UnexpectedEof, "Payload ended unexpectedly")
+        })?);
+    }
+    Ok(payload)
+}
+
+// The content of a file, memory mapped if the OS lets us
+enum MappedFile {
+    Mapped(Mmap),
+    // Pipes, some network file systems and special files can't be mapped,
+    // so as a last resort we read them piece by piece. Reading them into
+    // memory instead would defeat the point for files of several gigabytes
+    Buffered(BufReader<File>),
+}
+
+impl MappedFile {
+    fn open<P: AsRef<Path>>(path: P) -> io::Result<MappedFile> {
+        let file = File::open(path)?;
+        // Safety: the mapping is only valid as long as nobody truncates or
+        // changes the file behind our back. That is a promise the OS can't
+        // enforce, which is why creating a mapping is unsafe.
+        match unsafe { Mmap::map(&file) } {
+            Ok(mmap) => Ok(MappedFile::Mapped(mmap)),
+            Err(e) => {
+                eprintln!("Failed to map file, falling back to buffered reading: {}", e);
+                Ok(MappedFile::Buffered(BufReader::new(file)))
+            }
+        }
+    }
+
+    // Only a mapped file can hand out its bytes without copying them
+    fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            MappedFile::Mapped(mmap) => Some(mmap),
+            MappedFile::Buffered(_) => None,
+        }
+    }
+
+    // Reads the file from the start, with the typed accessors of ReadBytesExt
+    fn reader(&mut self) -> io::Result<Box<dyn BufRead + '_>> {
+        match self {
+            MappedFile::Mapped(mmap) => Ok(Box::new(ByteCursor::new(mmap))),
+            MappedFile::Buffered(reader) => {
+                match reader.seek(SeekFrom::Start(0)) {
+                    Ok(_) => {}
+                    // A pipe can only be read once anyway
+                    Err(ref e) if e.kind() == io::ErrorKind::NotSeekable => {}
+                    Err(e) => return Err(e),
+                }
+                Ok(Box::new(reader))
+            }
+        }
+    }
+
+    // Pipes don't know how much is still going to come
+    fn len(&self) -> io::Result<Option<u64>> {
+        match self {
+            MappedFile::Mapped(mmap) => Ok(Some(mmap.len() as u64)),
+            MappedFile::Buffered(reader) => {
+                let metadata = reader.get_ref().metadata()?;
+                Ok(if metadata.is_file() { Some(metadata.len()) } else { None })
+            }
+        }
+    }
+
+    fn is_mapped(&self) -> bool {
+        matches!(self, MappedFile::Mapped(_))
+    }
+}
+
+fn out_of_bounds(range: &Range<usize>, len: usize) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::UnexpectedEof,
+        format!("Range {:?} is out of bounds for {} bytes", range, len),
+    )
+}
+
+// Like data[range], but returns an error instead of panicking
+fn slice(data: &[u8], range: Range<usize>) -> io::Result<&[u8]> {
+    data.get(range.clone())
+        .ok_or_else(|| out_of_bounds(&range, data.len()))
+}
+
+// A cursor over borrowed bytes with the accessors of byteorder's ReadBytesExt.
+// Unlike io::Cursor, read_bytes hands out slices of the underlying data
+// instead of copying them into a buffer.
+struct ByteCursor<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ByteCursor<'a> {
+    fn new(data: &'a [u8]) -> ByteCursor<'a> {
+        ByteCursor { data, position: 0 }
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len() - self.position
+    }
+
+    // Borrow the next len bytes and move past them
+    fn read_bytes(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        let range = self.position..self.position.saturating_add(len);
+        let bytes = slice(self.data, range)?;
+        self.position += len;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> io::Result<u8> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_u16<E: ByteOrder>(&mut self) -> io::Result<u16> {
+        Ok(E::read_u16(self.read_bytes(2)?))
+    }
+
+    fn read_u32<E: ByteOrder>(&mut self) -> io::Result<u32> {
+        Ok(E::read_u32(self.read_bytes(4)?))
+    }
+
+    fn read_i32<E: ByteOrder>(&mut self) -> io::Result<i32> {
+        Ok(E::read_i32(self.read_bytes(4)?))
+    }
+
+    fn read_u64<E: ByteOrder>(&mut self) -> io::Result<u64> {
+        Ok(E::read_u64(self.read_bytes(8)?))
+    }
+
+    fn read_f32<E: ByteOrder>(&mut self) -> io::Result<f32> {
+        Ok(E::read_f32(self.read_bytes(4)?))
+    }
+
+    fn read_f64<E: ByteOrder>(&mut self) -> io::Result<f64> {
+        Ok(E::read_f64(self.read_bytes(8)?))
+    }
+}
+
+// Seeking works just like on io::Cursor
+impl Seek for ByteCursor<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => (self.data.len() as u64).checked_add_signed(offset),
+            SeekFrom::Current(offset) => (self.position as u64).checked_add_signed(offset),
+        };
+        match target {
+            Some(target) if target <= self.data.len() as u64 => {
+                self.position = target as usize;
+                Ok(target)
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Tried to seek outside of the data",
+            )),
+        }
+    }
+}
+
+// Implementing Read makes everything that works on readers,
+// including ReadBytesExt itself, work on the cursor too
+impl Read for ByteCursor<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = buf.len().min(self.remaining());
+        buf[..len].copy_from_slice(self.read_bytes(len)?);
+        Ok(len)
+    }
+}
+
+// The data is already in memory, so it's one big buffer
+impl BufRead for ByteCursor<'_> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        Ok(&self.data[self.position..])
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.position = (self.position + amount).min(self.data.len());
+    }
+}
@@ -0,0 +1,244 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn main() {
+    let path = "./foo.txt";
+
+    // Replacing a file atomically means readers either see
+    // the old content or the new one, never a mix of both
+    println!("Atomically replacing the content of '{}'", path);
+    atomic_write(path, b"Hello World!\n").expect("Failed to write file");
+    atomic_write(path, b"New content\n").expect("Failed to write file");
+    println!("{}", fs::read_to_string(path).expect("Failed to read file"));
+
+    // Several writers appending at the same time. Every record
+    // ends up in the file in one piece, no matter how they interleave
+    println!("Appending records from four threads");
+    let handles: Vec<_> = (0..4)
+        .map(|writer| {
+            thread::spawn(move || {
+                for record in 0..5 {
+                    let line = format!("writer {} record {}: {}", writer, record, "x".repeat(40));
+                    append_record(path, line.as_bytes()).expect("Failed to append record");
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().expect("Writer thread panicked");
+    }
+    let file = BufReader::new(File::open(path).expect("Failed to open file"));
+    let records = file.lines().map(|line| line.expect("Failed to read line")).collect::<Vec<_>>();
+    let intact = records.iter().skip(1).all(|line| line.ends_with(&"x".repeat(40)));
+    println!("The file now has {} lines, all records intact: {}", records.len(), intact);
+
+    // While one handle holds the exclusive lock, nobody else gets it
+    let guard = FileLock::exclusive(path, Some(Duration::from_secs(1))).expect("Failed to lock file");
+    println!("Holding an exclusive lock on '{}'", guard.path().display());
+    match FileLock::shared(path, Some(Duration::from_millis(200))) {
+        Ok(_) => println!("Unexpectedly got a shared lock"),
+        Err(e) => println!("Trying to read-lock it meanwhile fails: {}", e),
+    }
+    // Dropping the guard releases the lock
+    drop(guard);
+    let first = FileLock::shared(path, None).expect("Failed to lock file");
+    let second = FileLock::shared(path, None).expect("Failed to lock file");
+    println!("After releasing it, two readers can share it at once");
+    drop((first, second));
+}
+
+// Replace the content of a file so that a crash at any point leaves
+// either the complete old or the complete new version behind
+fn atomic_write<P: AsRef<Path>>(path: P, content: &[u8]) -> io::Result<()> {
+    let path = path.as_ref();
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    // The temporary file has to live in the same directory,
+    // because rename() is only atomic within one file system
+    let temporary = temporary_path(path);
+    let result = (|| {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temporary)?;
+        // Keep the permissions of the file we are replacing
+        if let Ok(metadata) = fs::metadata(path) {
+            file.set_permissions(metadata.permissions())?;
+        }
+        file.write_all(content)?;
+        // Make sure the data is on disk before it becomes visible under the real name
+        file.sync_all()?;
+        fs::rename(&temporary, path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temporary);
+        return result;
+    }
+
+    // The rename itself is only durable once the directory is synced
+    sync_dir(dir)
+}
+
+fn temporary_path(path: &Path) -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let temporary = format!(
+        ".{}.{}.{}.tmp",
+        name,
+        process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    );
+    path.with_file_name(temporary)
+}
+
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    File::open(dir)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> io::Result<()> {
+    // Other platforms don't let us open directories,
+    // the rename is as durable as it gets there
+    Ok(())
+}
+
+// Append one record to a file in a single piece,
+// even when other processes append to it at the same time
+fn append_record<P: AsRef<Path>>(path: P, record: &[u8]) -> io::Result<()> {
+    let path = path.as_ref();
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    // Build the whole record first, so it goes out in one write call
+    let mut buffer = Vec::with_capacity(record.len() + 1);
+    buffer.extend_from_slice(record);
+    if !record.ends_with(b"\n") {
+        buffer.push(b'\n');
+    }
+    // O_APPEND alone makes small writes atomic on local disks,
+    // the lock extends that to big records and network file systems
+    let _guard = FileLock::lock(&file, path, LockKind::Exclusive, None)?;
+    file.write_all(&buffer)?;
+    file.sync_data()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LockKind {
+    Shared,
+    Exclusive,
+}
+
+// An advisory lock on a file that is released when it goes out of scope.
+// Advisory means that only processes that also lock the file are kept out.
+struct FileLock {
+    file: File,
+    path: PathBuf,
+}
+
+impl FileLock {
+    // Many readers can hold a shared lock at the same time
+    fn shared<P: AsRef<Path>>(path: P, timeout: Option<Duration>) -> io::Result<FileLock> {
+        let file = File::open(path.as_ref())?;
+        FileLock::acquire(file, path.as_ref(), LockKind::Shared, timeout)
+    }
+
+    // An exclusive lock keeps everybody else out
+    fn exclusive<P: AsRef<Path>>(path: P, timeout: Option<Duration>) -> io::Result<FileLock> {
+        let file = OpenOptions::new().read(true).write(true).open(path.as_ref())?;
+        FileLock::acquire(file, path.as_ref(), LockKind::Exclusive, timeout)
+    }
+
+    // Lock a file that is already open, sharing its descriptor
+    fn lock(file: &File, path: &Path, kind: LockKind, timeout: Option<Duration>) -> io::Result<FileLock> {
+        FileLock::acquire(file.try_clone()?, path, kind, timeout)
+    }
+
+    // Without a timeout we wait for as long as it takes
+    fn acquire(file: File, path: &Path, kind: LockKind, timeout: Option<Duration>) -> io::Result<FileLock> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut backoff = Duration::from_millis(1);
+        loop {
+            match try_lock(&file, kind) {
+                Ok(()) => {
+                    return Ok(FileLock {
+                        file,
+                        path: path.to_path_buf(),
+                    })
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(e) => return Err(e),
+            }
+            if let Some(deadline) = deadline {
+                if Instant::now() >= deadline {
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        format!("Timed out waiting for a lock on {}", path.display()),
+                    ));
+                }
+            }
+            // Poll with a growing pause so waiting doesn't burn the CPU
+            thread::sleep(backoff);
+            backoff = (backoff * 2).min(Duration::from_millis(50));
+        }
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        // Closing the file would release the lock as well,
+        // but unlocking explicitly documents what happens
+        let _ = unlock(&self.file);
+    }
+}
+
+#[cfg(unix)]
+fn try_lock(file: &File, kind: LockKind) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+    let operation = match kind {
+        LockKind::Shared => libc::LOCK_SH,
+        LockKind::Exclusive => libc::LOCK_EX,
+    };
+    // LOCK_NB makes flock fail with EWOULDBLOCK instead of waiting,
+    // which is what allows us to implement timeouts
+    let result = unsafe { libc::flock(file.as_raw_fd(), operation | libc::LOCK_NB) };
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(unix)]
+fn unlock(file: &File) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+    let result = unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_UN) };
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(unix))]
+fn try_lock(_file: &File, _kind: LockKind) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Other, "flock is only available on unix"))
+}
+
+#[cfg(not(unix))]
+fn unlock(_file: &File) -> io::Result<()> {
+    Ok(())
+}
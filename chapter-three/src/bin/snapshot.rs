@@ -0,0 +1,324 @@
+use serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::process;
+use std::thread;
+use std::time::{Duration, UNIX_EPOCH};
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let hash = args.iter().any(|arg| arg == "--hash");
+    let args: Vec<&str> = args
+        .iter()
+        .map(String::as_str)
+        .filter(|&arg| arg != "--hash")
+        .collect();
+
+    let result = match args.as_slice() {
+        ["take", dir, output] => take(Path::new(dir), Path::new(output), hash),
+        ["diff", old, new] => diff_files(Path::new(old), Path::new(new)),
+        ["watch", dir] => watch(Path::new(dir), Duration::from_secs(2), hash),
+        ["watch", dir, "--interval", seconds] => match seconds.parse() {
+            Ok(seconds) => watch(Path::new(dir), Duration::from_secs(seconds), hash),
+            Err(_) => Err(io::Error::new(io::ErrorKind::InvalidInput, "Interval must be a number of seconds")),
+        },
+        _ => {
+            eprintln!(
+                "Usage:\n  \
+                 snapshot take <directory> <snapshot.json> [--hash]\n  \
+                 snapshot diff <old.json> <new.json>\n  \
+                 snapshot watch <directory> [--interval <seconds>] [--hash]\n\
+                 \n\
+                 --hash records a SHA-256 of every file, which makes renames detectable"
+            );
+            process::exit(2);
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Kind {
+    File,
+    Directory,
+    Symlink,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct EntryInfo {
+    kind: Kind,
+    size: u64,
+    // Nanoseconds since the unix epoch, so quick successive writes are told apart
+    mtime: u128,
+    readonly: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mode: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hash: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Snapshot {
+    root: String,
+    // Paths relative to the root. A BTreeMap keeps the file diffable and the output sorted
+    entries: BTreeMap<String, EntryInfo>,
+}
+
+impl Snapshot {
+    fn take(root: &Path, hash: bool) -> io::Result<Snapshot> {
+        let mut entries = BTreeMap::new();
+        for entry in WalkDir::new(root).min_depth(1) {
+            let entry = match entry {
+                Ok(entry) => entry,
+                // Files can vanish between listing and reading them, that's no reason to give up
+                Err(e) => {
+                    eprintln!("Skipping: {}", e);
+                    continue;
+                }
+            };
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    eprintln!("Skipping {}: {}", entry.path().display(), e);
+                    continue;
+                }
+            };
+            let kind = if metadata.is_dir() {
+                Kind::Directory
+            } else if metadata.file_type().is_symlink() {
+                Kind::Symlink
+            } else {
+                Kind::File
+            };
+            let hash = if hash && kind == Kind::File {
+                hash_file(entry.path()).ok()
+            } else {
+                None
+            };
+            let relative = entry
+                .path()
+                .strip_prefix(root)
+                .expect("WalkDir returned a path outside of its root");
+            entries.insert(
+                relative.to_string_lossy().into_owned(),
+                EntryInfo {
+                    kind,
+                    size: metadata.len(),
+                    mtime: metadata
+                        .modified()
+                        .ok()
+                        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                        .map(|duration| duration.as_nanos())
+                        .unwrap_or(0),
+                    readonly: metadata.permissions().readonly(),
+                    mode: mode_of(&metadata),
+                    hash,
+                },
+            );
+        }
+        Ok(Snapshot {
+            root: root.to_string_lossy().into_owned(),
+            entries,
+        })
+    }
+
+    fn save(&self, path: &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.flush()
+    }
+
+    fn load(path: &Path) -> io::Result<Snapshot> {
+        let reader = BufReader::new(File::open(path)?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+}
+
+#[cfg(unix)]
+fn mode_of(metadata: &fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    Some(metadata.permissions().mode() & 0o7777)
+}
+
+#[cfg(not(unix))]
+fn mode_of(_metadata: &fs::Metadata) -> Option<u32> {
+    None
+}
+
+fn hash_file(path: &Path) -> io::Result<String> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut hasher = Sha256::new();
+    io::copy(&mut reader, &mut hasher)?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+#[derive(Debug, PartialEq)]
+enum Change {
+    Added(String),
+    Removed(String),
+    Modified { path: String, what: Vec<&'static str> },
+    Renamed { from: String, to: String },
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Change::Added(path) => write!(f, "+ {}", path),
+            Change::Removed(path) => write!(f, "- {}", path),
+            Change::Modified { path, what } => write!(f, "~ {} ({})", path, what.join(", ")),
+            Change::Renamed { from, to } => write!(f, "> {} -> {}", from, to),
+        }
+    }
+}
+
+// What differs between two versions of the same entry
+fn compare(old: &EntryInfo, new: &EntryInfo) -> Vec<&'static str> {
+    let mut what = Vec::new();
+    if old.kind != new.kind {
+        what.push("type");
+    }
+    // Directories change their size and mtime whenever a child does,
+    // which the added and removed children already tell us about
+    let is_directory = new.kind == Kind::Directory;
+    if old.size != new.size && !is_directory {
+        what.push("size");
+    }
+    if old.mtime != new.mtime && !is_directory {
+        what.push("mtime");
+    }
+    if old.readonly != new.readonly || old.mode != new.mode {
+        what.push("permissions");
+    }
+    if let (Some(old_hash), Some(new_hash)) = (&old.hash, &new.hash) {
+        if old_hash != new_hash {
+            what.push("content");
+        }
+    }
+    what
+}
+
+fn diff(old: &Snapshot, new: &Snapshot) -> Vec<Change> {
+    let mut changes = Vec::new();
+    let mut added = Vec::new();
+    let mut removed = Vec::new();
+
+    for (path, old_info) in &old.entries {
+        match new.entries.get(path) {
+            Some(new_info) => {
+                let what = compare(old_info, new_info);
+                if !what.is_empty() {
+                    changes.push(Change::Modified { path: path.clone(), what });
+                }
+            }
+            None => removed.push(path),
+        }
+    }
+    for path in new.entries.keys() {
+        if !old.entries.contains_key(path) {
+            added.push(path);
+        }
+    }
+
+    // A file that vanished in one place and showed up with the same
+    // content in another was most likely renamed. Empty files and content
+    // that several files share are ambiguous, so those stay adds and removes
+    let old_by_hash = unique_content(old);
+    let new_by_hash = unique_content(new);
+    let mut renamed_from = Vec::new();
+    let mut renamed_to = Vec::new();
+    for path in &removed {
+        let to = match &old.entries[*path].hash {
+            Some(hash) if old_by_hash.contains_key(hash.as_str()) => new_by_hash.get(hash.as_str()),
+            _ => None,
+        };
+        if let Some(to) = to.filter(|to| added.contains(to)) {
+            changes.push(Change::Renamed {
+                from: (*path).clone(),
+                to: (*to).clone(),
+            });
+            renamed_from.push(*path);
+            renamed_to.push(*to);
+        }
+    }
+
+    changes.extend(
+        added
+            .into_iter()
+            .filter(|path| !renamed_to.contains(path))
+            .map(|path| Change::Added(path.clone())),
+    );
+    changes.extend(
+        removed
+            .into_iter()
+            .filter(|path| !renamed_from.contains(path))
+            .map(|path| Change::Removed(path.clone())),
+    );
+    changes
+}
+
+// Content hashes that exactly one non-empty file in the snapshot has
+fn unique_content(snapshot: &Snapshot) -> HashMap<&str, &String> {
+    let mut paths: HashMap<&str, Vec<&String>> = HashMap::new();
+    for (path, info) in &snapshot.entries {
+        if let (Some(hash), true) = (&info.hash, info.size > 0) {
+            paths.entry(hash.as_str()).or_default().push(path);
+        }
+    }
+    paths
+        .into_iter()
+        .filter_map(|(hash, paths)| match paths[..] {
+            [path] => Some((hash, path)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn take(dir: &Path, output: &Path, hash: bool) -> io::Result<()> {
+    let snapshot = Snapshot::take(dir, hash)?;
+    snapshot.save(output)?;
+    println!(
+        "Saved {} entries of {} to {}",
+        snapshot.entries.len(),
+        dir.display(),
+        output.display()
+    );
+    Ok(())
+}
+
+fn diff_files(old: &Path, new: &Path) -> io::Result<()> {
+    let changes = diff(&Snapshot::load(old)?, &Snapshot::load(new)?);
+    for change in &changes {
+        println!("{}", change);
+    }
+    println!("{} changes", changes.len());
+    Ok(())
+}
+
+// Poll the directory and report whatever changed since the last look
+fn watch(dir: &Path, interval: Duration, hash: bool) -> io::Result<()> {
+    println!("Watching {} every {:?}, stop with Ctrl C", dir.display(), interval);
+    let mut previous = Snapshot::take(dir, hash)?;
+    loop {
+        thread::sleep(interval);
+        let current = Snapshot::take(dir, hash)?;
+        for change in diff(&previous, &current) {
+            println!("{}", change);
+        }
+        previous = current;
+    }
+}